@@ -0,0 +1,25 @@
+#![no_main]
+
+// arbitrary bytes as the rom, run until the machine halts or the step limit is reached, any panic is a bug
+// run with: cargo +nightly fuzz run cycle
+use libfuzzer_sys::fuzz_target;
+
+use ch8_rs::{Chip8, MEMORY_SIZE, ROM_START};
+
+// enough for most inputs to run into every instruction they contain, small enough to keep the fuzzer fast
+static MAX_CYCLES: usize = 10_000;
+
+fuzz_target!(|data: &[u8]| {
+    let mut ch8 = Chip8::new();
+    if ch8.load_rom_bytes(&data[..data.len().min(MEMORY_SIZE - ROM_START)]).is_err() {
+        return;
+    }
+
+    for _ in 0..MAX_CYCLES {
+        if ch8.is_halted() {
+            break;
+        }
+
+        let _ = ch8.cycle();
+    }
+});
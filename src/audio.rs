@@ -0,0 +1,19 @@
+use sdl2::audio::AudioCallback;
+
+// square wave played while the sound timer is active, the device is paused and resumed instead of recreated
+pub struct SquareWave {
+    pub phase_inc: f32, // tone frequency divided by the sample rate
+    pub phase: f32,
+    pub volume: f32
+}
+
+impl AudioCallback for SquareWave {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = if self.phase < 0.5 { self.volume } else { -self.volume };
+            self.phase = (self.phase + self.phase_inc) % 1.0;
+        }
+    }
+}
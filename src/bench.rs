@@ -0,0 +1,89 @@
+use anyhow::Result;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use ch8_rs::ch8::Chip8;
+
+// how often the elapsed time is checked, keeps Instant::now out of the hot loop
+static CHECK_INTERVAL: usize = 1024;
+
+// one rom's run, error is set when the rom couldn't be loaded or the machine halted before the time was up
+struct RomResult {
+    instructions: u64,
+    elapsed: Duration,
+    error: Option<String>
+}
+
+// runs the rom headless until the duration is over or the machine halts, only executed instructions are counted
+fn run_rom(rom: &str, duration: Duration) -> RomResult {
+    let mut ch8 = Chip8::default();
+    if let Err(e) = ch8.read_rom(rom) {
+        return RomResult { instructions: 0, elapsed: Duration::from_secs(0), error: Some(e.to_string()) };
+    }
+
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        if let Err(e) = ch8.run_cycles(CHECK_INTERVAL) {
+            return RomResult { instructions: ch8.instructions(), elapsed: start.elapsed(), error: Some(e.to_string()) };
+        }
+    }
+
+    RomResult { instructions: ch8.instructions(), elapsed: start.elapsed(), error: None }
+}
+
+// instructions per second, 0 for a rom that never ran
+fn ips(instructions: u64, elapsed: Duration) -> f64 {
+    if elapsed.as_secs_f64() > 0.0 { instructions as f64 / elapsed.as_secs_f64() } else { 0.0 }
+}
+
+// runs every rom headless for the given duration and prints the instructions per second for each rom and overall
+// a rom that fails to load or halts is reported on its row, the rest of the suite still runs
+pub fn benchmark_suite(roms: &[&str], duration: Duration) -> Result<()> {
+    let mut total_instructions: u64 = 0;
+    let mut total_time = Duration::from_secs(0);
+
+    println!("{:<24} {:>14} {:>14}", "rom", "instructions", "ips");
+
+    for rom in roms {
+        let result = run_rom(rom, duration);
+        total_instructions += result.instructions;
+        total_time += result.elapsed;
+
+        let name = Path::new(rom).file_name().map_or(String::from(*rom), |n| n.to_string_lossy().into_owned());
+        match result.error {
+            Some(e) => println!("{:<24} {:>14} {:>14.0}  {}", name, result.instructions, ips(result.instructions, result.elapsed), e),
+            None => println!("{:<24} {:>14} {:>14.0}", name, result.instructions, ips(result.instructions, result.elapsed))
+        }
+    }
+
+    println!("{:<24} {:>14} {:>14.0}", "total", total_instructions, ips(total_instructions, total_time));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a rom returning with an empty stack halts on its second instruction, only the first one is counted
+    #[test]
+    fn reports_halting_rom() {
+        let path = std::env::temp_dir().join(std::format!("ch8-rs-bench-{}.ch8", std::process::id()));
+        std::fs::write(&path, [0x60, 0x01, 0x00, 0xEE]).unwrap();
+
+        let result = run_rom(path.to_str().unwrap(), Duration::from_secs(1));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.instructions, 1);
+        assert!(result.elapsed < Duration::from_secs(1));
+        assert_eq!(result.error, Some(String::from("stack underflow, return without a call")));
+    }
+
+    #[test]
+    fn reports_missing_rom() {
+        let result = run_rom("does-not-exist.ch8", Duration::from_secs(1));
+
+        assert_eq!(result.instructions, 0);
+        assert_eq!(result.error, Some(String::from("rom not found: does-not-exist.ch8")));
+    }
+}
@@ -1,11 +1,32 @@
 use anyhow::{Context, Result, bail};
-use std::{fs::{File}, io::Read};
-use rand::{Rng, prelude::ThreadRng};
+use log::{Level, log_enabled, trace, warn};
+use std::{fs::{self, File}, io::Read, path::Path, time::Duration};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::error::Ch8Error;
+use crate::disasm;
+use crate::opcode::{DecodedInstr, Opcode, decode};
+use crate::render::{BACKGROUND, FOREGROUND, PLANE_2, PLANES_BOTH, Renderer, render_argb};
+use crate::savestate;
+use crate::trace::{RegisterDelta, TraceEntry, TraceFilter, TraceFormat};
 
 pub static VIDEO_WIDTH: usize = 64;
 pub static VIDEO_HEIGHT: usize = 32;
+pub static HIRES_VIDEO_WIDTH: usize = 128; // super-chip high resolution mode, see 00FF
+pub static HIRES_VIDEO_HEIGHT: usize = 64;
+
+// memory layout
+pub static MEMORY_SIZE: usize = 4096;
+pub static MAX_MEMORY_SIZE: usize = 0x10000; // everything the 16 bit index register can address
+pub static FONT_START: usize = 0x50;
+pub static ROM_START: usize = 0x200;
 
-static CH8_FONT: &'static [u8] = &[                    
+// the delay and sound timers count down at 60hz of emulated time, however many instructions run per second
+pub static TIMER_RATE: u32 = 60;
+pub static INSTRUCTION_RATE: u32 = 700; // default instructions per second
+static PC_MASK: u16 = 0x0FFF; // the pc addresses 12 bits and wraps around at the top of memory
+
+static CH8_FONT: &[u8] = &[                    
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -24,622 +45,2254 @@ static CH8_FONT: &'static [u8] = &[
     0xF0, 0x80, 0xF0, 0x80, 0x80  // F
 ];
 
+// behaviour that differs between chip8 interpreters, read live by the instruction handlers
+#[derive(Clone, Copy, Default)]
+pub struct Quirks {
+    pub jump_uses_vx: bool, // Bxnn jumps to xnn plus register x instead of nnn plus register 0
+    pub shift_vf_keeps_result: bool, // 8xy6/8xyE on register F keep the shifted value instead of the shifted out bit
+    pub schip_collision_count: bool, // in hi-res Dxyn sets VF to the number of rows that collided or wrapped off the bottom (super-chip)
+    pub shift_uses_vy: bool, // 8xy6/8xyE shift register y into register x instead of shifting register x in place (cosmac vip)
+    pub load_store_increments_i: bool, // Fx55/Fx65 leave the index register pointing past the last register (cosmac vip)
+    pub index_overflow_sets_vf: bool, // Fx1E sets VF when the index register goes past 0xFFF (amiga interpreter)
+}
+
+// borrows one boolean quirk out of a Quirks
+pub type QuirkFlag = fn(&mut Quirks) -> &mut bool;
+
+// every boolean quirk by name, for tooling that toggles or enumerates quirks
+pub static QUIRK_FLAGS: &[(&str, QuirkFlag)] = &[
+    ("jump_uses_vx", |q| &mut q.jump_uses_vx),
+    ("shift_vf_keeps_result", |q| &mut q.shift_vf_keeps_result),
+    ("schip_collision_count", |q| &mut q.schip_collision_count),
+    ("shift_uses_vy", |q| &mut q.shift_uses_vy),
+    ("load_store_increments_i", |q| &mut q.load_store_increments_i),
+    ("index_overflow_sets_vf", |q| &mut q.index_overflow_sets_vf)
+];
+
+// copy of the full machine state, see Chip8::snapshot and Chip8::restore
+#[derive(Clone, PartialEq)]
+pub struct Chip8State {
+    pub registers: Vec<u8>,
+    pub memory: Vec<u8>,
+    pub stack: Vec<u16>,
+    pub display: Vec<u32>,
+    pub hires: bool,
+    pub planes: u8,
+    pub kp_input: Vec<u32>,
+    pub index: u16,
+    pub pc: u16,
+    pub sp: u8,
+    pub delay_timer: u8,
+    pub sound_timer: u8
+}
+
+// indices that differ between two machines, see Chip8::diff
+#[derive(Default, PartialEq)]
+pub struct StateDiff {
+    pub registers: Vec<usize>,
+    pub memory: Vec<usize>, // addresses
+    pub stack: Vec<usize>,
+    pub display: Vec<usize> // pixel indices
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty() && self.memory.is_empty() && self.stack.is_empty() && self.display.is_empty()
+    }
+}
+
+// a write by the rom to a watched address, see Chip8::add_watchpoint
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WatchHit {
+    pub pc: u16, // instruction that wrote it
+    pub address: u16,
+    pub old: u8,
+    pub new: u8
+}
+
 pub struct Chip8 {
     registers: Vec<u8>, // 16 u8 registers from V(x0) to V(xF)
     memory: Vec<u8>, // total chip8 memory is 4096 (4k)
     stack: Vec<u16>, // stack can hold 16 u16's
+    rom_len: usize, // size of the loaded rom in bytes
+    loaded_rom: Vec<u8>, // the last rom loaded, copied back into memory by reset and reload
+    start_pc: u16, // where execution starts after a boot or reset
 
-    display: Vec<u32>, // 64x32 pixel display
+    display: Vec<u32>, // sized for 128x64, only the first width * height pixels of the active resolution are used
+                       // each pixel holds one bit per plane, so its value is a palette index from 0 to 3
+    hires: bool, // 128x64 super-chip mode instead of 64x32
+    planes: u8, // xo-chip bitplanes drawn and cleared by Dxyn, 00E0 and the scrolls, bit 0 is plane 1 and bit 1 plane 2
 
     kp_input: Vec<u32>, // keypad input
+    kp_latched: Vec<bool>, // keys pressed since an instruction last read them, so a press and release between cycles isn't lost
+    kp_released: Vec<bool>, // keys released since Fx0A started waiting
+    waiting_for_key: bool, // Fx0A is waiting for a key to be released
+    kp_queue: Vec<(u64, usize, bool)>, // scripted key events as (instruction count, key, pressed)
+    rpl_flags: [u8; 8], // super-chip user flags written by Fx75 and read by Fx85, outlive resets like on the hp48
+    instructions: u64, // instructions executed so far
 
     index: u16, // instruction index
-    pc: usize,
+    pc: u16,
     sp: u8,
 
     delay_timer: u8,
     sound_timer: u8,
+    timers_frozen: bool,
+    timer_accumulator: Duration, // emulated time since the last timer tick
+    in_frame: bool, // run_frame ticks the timers itself, so cycles leave them alone
+    instruction_rate: u32, // instructions per emulated second
 
     should_draw: bool,
-    debug_enabled: bool,
-    rng: ThreadRng
+    strict: bool, // halt on instructions that are not supported instead of skipping them
+    halted: bool,
+    halt_on_rom_end: bool, // halt when the pc runs past the loaded rom instead of executing zeroed memory
+    no_font: bool, // the fontset was unloaded, reset keeps the font area zeroed
+    trace_format: TraceFormat,
+    trace_filter: Option<TraceFilter>, // only instructions of these families are traced
+    breakpoints: Vec<u16>, // addresses run_until_breakpoint stops at
+    watchpoints: Vec<u16>, // memory addresses whose writes are recorded in watch_hits
+    watch_hits: Vec<WatchHit>, // writes to watched addresses since take_watch_hits was last called
+    quirks: Quirks,
+    rng: StdRng
 }
 
-impl Chip8 {
-    pub fn new(debug_enabled: bool) -> Self { 
-        let mut s = Self { 
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Configuration for a new `Chip8`, every setting starts out at the same default `Chip8::new` uses.
+///
+/// ```
+/// use ch8_rs::{Chip8Builder, Quirks};
+///
+/// let quirks = Quirks { jump_uses_vx: true, ..Quirks::default() };
+/// let mut ch8 = Chip8Builder::new().quirks(quirks).seed(42).clock_hz(1000).start_pc(0x300).build();
+///
+/// assert_eq!(ch8.get_pc(), 0x300);
+/// assert_eq!(ch8.cycles_per_frame(), 17);
+/// assert!(ch8.quirks().jump_uses_vx);
+/// ```
+pub struct Chip8Builder {
+    quirks: Quirks,
+    seed: Option<u64>, // random seed for Cxnn, seeded from the os when not set
+    clock_hz: u32,
+    start_pc: u16
+}
+
+impl Chip8Builder {
+    pub fn new() -> Self {
+        Self {
+            quirks: Quirks::default(),
+            seed: None,
+            clock_hz: INSTRUCTION_RATE,
+            start_pc: ROM_START as u16
+        }
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    // instructions per second, the timers tick at 60hz relative to it
+    pub fn clock_hz(mut self, clock_hz: u32) -> Self {
+        self.clock_hz = clock_hz;
+        self
+    }
+
+    pub fn start_pc(mut self, start_pc: u16) -> Self {
+        self.start_pc = start_pc;
+        self
+    }
+
+    pub fn build(self) -> Chip8 {
+        let mut s = Chip8 { 
             registers: vec![0; 16],
-            memory: vec![0; 4096],
+            memory: vec![0; MEMORY_SIZE],
             stack: vec![0; 16],
-            display: vec![0; VIDEO_HEIGHT * VIDEO_WIDTH],
+            rom_len: 0,
+            loaded_rom: Vec::new(),
+            start_pc: self.start_pc & PC_MASK,
+            display: vec![0; HIRES_VIDEO_HEIGHT * HIRES_VIDEO_WIDTH],
+            hires: false,
+            planes: 1,
             kp_input: vec![0; 16],
+            kp_latched: vec![false; 16],
+            kp_released: vec![false; 16],
+            waiting_for_key: false,
+            kp_queue: Vec::new(),
+            rpl_flags: [0; 8],
+            instructions: 0,
             index: 0,
-            pc: 0x200,
+            pc: self.start_pc & PC_MASK,
             sp: 0,
             delay_timer: 0,
             sound_timer: 0,
+            timers_frozen: false,
+            timer_accumulator: Duration::from_secs(0),
+            in_frame: false,
+            instruction_rate: self.clock_hz.max(1),
             should_draw: false,
-            debug_enabled,
-            rng: rand::thread_rng()
+            strict: false,
+            halted: false,
+            halt_on_rom_end: false,
+            no_font: false,
+            trace_format: TraceFormat::Text,
+            trace_filter: None,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            watch_hits: Vec::new(),
+            quirks: self.quirks,
+            rng: self.seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64)
         };
 
-        // load fontset into memory
-        for ii in 0..80 {
-            s.memory[0x50 + ii] = CH8_FONT[ii];
-        }
-
+        s.load_font();
         s
     }
+}
 
-    pub fn read_rom(&mut self, path: &str) -> Result<()> {
-        let mut buffer = Vec::new();
-        let mut file = File::open(path).context("invalid rom path supplied")?;
+impl Default for Chip8Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        file.read_to_end(&mut buffer).context("failed to read rom file")?;
+impl Chip8 {
+    pub fn new() -> Self {
+        Chip8Builder::new().build()
+    }
 
-        let mut j = 512;
-        for ii in 0..buffer.len() {
-            if j >= 4096 {
-                bail!("file is too big to load into memory");
-            }
+    // copies the built-in fontset into memory
+    fn load_font(&mut self) {
+        self.memory[FONT_START..FONT_START + CH8_FONT.len()].copy_from_slice(CH8_FONT);
+    }
 
-            self.memory[j] = buffer[ii];
-            j += 1;
+    // clears the built-in fontset, for roms that bring their own glyphs
+    pub fn unload_font(&mut self) {
+        self.no_font = true;
+        for ii in 0..CH8_FONT.len() {
+            self.memory[FONT_START + ii] = 0;
         }
-
-        Ok(())
     }
 
-    pub fn cycle(&mut self) {      
-        let opcode = (i32::from(self.memory[self.pc]) << 8) | i32::from(self.memory[self.pc + 1]);
-        let instruction = shift_i32(opcode, 12, 0xF000);
+    pub fn read_rom(&mut self, path: &str) -> Result<(), Ch8Error> {
+        let mut buffer = Vec::new();
+        let mut file = File::open(path).map_err(|_| Ch8Error::RomNotFound(String::from(path)))?;
 
-        match instruction {
-            0 => {
-                match opcode {
-                    0x00E0 => self.cls(),
-                    0x00EE => self.ret(),
-                    _ => println!("missing(0) -> {}", opcode)
-                }
-            }
-            1 => self.jmp(opcode & 0x0FFF),
-            2 => self.call(opcode & 0x0FFF),
-            3 => self.se_val(shift_u8(opcode, 8, 0x0F00), (opcode & 0x00FF) as u8),
-            4 => self.sne_val(shift_u8(opcode, 8, 0x0F00), (opcode & 0x00FF) as u8),
-            5 => self.se_reg(shift_u8(opcode, 8, 0x0F00), shift_u8(opcode, 4, 0x00F0)),
-            6 => self.ld_reg( shift_u8(opcode, 8, 0x0F00), (opcode & 0x00FF) as u8),
-            7 => self.add_val(shift_u8(opcode, 8, 0x0F00), (opcode & 0x00FF) as u8),
-            8 => {
-                match opcode & 0x000F {
-                    0 => self.copy(shift_u8(opcode, 8, 0x0F00), shift_u8(opcode, 4, 0x00F0)),
-                    1 => self.or(shift_u8(opcode, 8, 0x0F00), shift_u8(opcode, 4, 0x00F0)),
-                    2 => self.and(shift_u8(opcode, 8, 0x0F00), shift_u8(opcode, 4, 0x00F0)),
-                    3 => self.xor(shift_u8(opcode, 8, 0x0F00), shift_u8(opcode, 4, 0x00F0)),
-                    4 => self.add_reg(shift_u8(opcode, 8, 0x0F00), shift_u8(opcode, 4, 0x00F0)),
-                    5 => self.sub_regxy(shift_u8(opcode, 8, 0x0F00), shift_u8(opcode, 4, 0x00F0)),
-                    6 => self.shift_r(shift_u8(opcode, 8, 0x0F00)),
-                    7 => self.sub_regyx(shift_u8(opcode, 8, 0x0F00), shift_u8(opcode, 4, 0x00F0)),
-                    14 => self.shift_l(shift_u8(opcode, 8, 0x0F00)),
-                    _ => println!("missing(8) -> {}", shift_i32(opcode, 0, 0x00FF))
-                }
-            }
-            9 => self.sne_reg(shift_u8(opcode, 8, 0x0F00), shift_u8(opcode, 4, 0x00F0)),
-            10 => self.ld_indx(opcode & 0x0FFF),
-            11 => self.jmpadd(opcode & 0x0FFF),
-            12 => self.rand_and(shift_u8(opcode, 8, 0x0F00), (opcode & 0x00FF) as u8),
-            13 => self.draw_pixel(shift_u8(opcode, 8, 0x0F00), shift_u8(opcode, 4, 0x00F0), opcode & 0x000F),
-            14 => {
-                match opcode & 0x00FF {
-                    158 => self.se_kp(shift_u8(opcode, 8, 0x0F00)),
-                    161 => self.sne_kp(shift_u8(opcode, 8, 0x0F00)),
-                    _ => println!("missing(E) -> {}", shift_i32(opcode, 0, 0x00FF))
-                }
-            }
-            15 => {
-                match opcode & 0x00FF {
-                    7 => self.get_delay(shift_u8(opcode, 8, 0x0F00)),
-                    10 => self.wait_key(shift_u8(opcode, 8, 0x0F00)),
-                    21 => self.set_delay(shift_u8(opcode, 8, 0x0F00)),
-                    24 => self.set_sound(shift_u8(opcode, 8, 0x0F00)),
-                    30 => self.add_indx(shift_u8(opcode, 8, 0x0F00)),
-                    41 => self.get_font_chr(shift_u8(opcode, 8, 0x0F00)),
-                    51 => self.encode_save(shift_u8(opcode, 8, 0x0F00)),
-                    85 => self.save(shift_u8(opcode, 8, 0x0F00)),
-                    101 => self.load(shift_u8(opcode, 8, 0x0F00)),
-                    _ => println!("missing(F) -> {}", opcode & 0x00FF)
-                }
-            }
-            _ => println!("unimplemented instruction {}", instruction)
-        }
+        file.read_to_end(&mut buffer).map_err(|e| Ch8Error::RomUnreadable(e.to_string()))?;
 
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
+        self.load_rom_bytes(&buffer)
+    }
+
+    // copies a rom into memory at the rom start, for roms that don't come from a file
+    pub fn load_rom_bytes(&mut self, bytes: &[u8]) -> Result<(), Ch8Error> {
+        if ROM_START + bytes.len() > self.memory.len() {
+            return Err(Ch8Error::RomTooLarge(bytes.len()));
         }
 
-        if self.sound_timer > 0 {
-            if self.sound_timer == 1 {
-                print!("_______________________________beep")
-            }
+        self.memory[ROM_START..ROM_START + bytes.len()].copy_from_slice(bytes);
+        self.rom_len = bytes.len();
+        self.loaded_rom.clear();
+        self.loaded_rom.extend_from_slice(bytes);
 
-            self.sound_timer -= 1;
-        }
+        Ok(())
     }
 
-    pub fn should_draw(&self) -> bool {
-        self.should_draw
+    // reboots the machine into the state right after the current rom was loaded, reusing every buffer
+    // memory is cleared before the font and the rom are copied back, so self-modified code and data are gone as well
+    // configuration (quirks, clock, breakpoints, memory size) and the rpl flags survive like on the hp48
+    pub fn reset(&mut self) {
+        self.registers.iter_mut().for_each(|v| *v = 0);
+        self.memory.iter_mut().for_each(|b| *b = 0);
+        self.stack.iter_mut().for_each(|v| *v = 0);
+        self.display.iter_mut().for_each(|p| *p = 0);
+        self.hires = false;
+        self.planes = 1;
+
+        self.kp_input.iter_mut().for_each(|k| *k = 0);
+        self.kp_latched.iter_mut().for_each(|k| *k = false);
+        self.kp_released.iter_mut().for_each(|k| *k = false);
+        self.waiting_for_key = false;
+        self.instructions = 0;
+
+        self.index = 0;
+        self.pc = self.start_pc;
+        self.sp = 0;
+
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.timer_accumulator = Duration::from_secs(0);
+
+        self.halted = false;
+        self.should_draw = true; // the cleared display still has to reach the screen
+        self.watch_hits.clear();
+
+        if !self.no_font {
+            self.load_font();
+        }
+        self.reload();
     }
 
-    pub fn set_should_draw(&mut self, should_draw: bool) {
-        self.should_draw = should_draw;
+    // copies the loaded rom back over the program area, which is zeroed first, without touching the rest of the machine
+    // undoes self-modifying code and data the rom stored after itself
+    pub fn reload(&mut self) {
+        self.memory[ROM_START..].iter_mut().for_each(|b| *b = 0);
+        self.memory[ROM_START..ROM_START + self.loaded_rom.len()].copy_from_slice(&self.loaded_rom);
     }
 
-    pub fn get_display(&self, index: usize) -> u32 {
-        self.display[index]
+    // the rom as it was loaded, unlike rom_bytes this doesn't change when the rom modifies itself
+    pub fn loaded_rom(&self) -> &[u8] {
+        &self.loaded_rom
     }
 
-    pub fn set_input(&mut self, index: usize, is_pressed: bool) {
-        self.kp_input[index] = is_pressed as u32;
+    // opcode at the program counter, stored big endian
+    fn fetch(&self) -> u16 {
+        self.opcode_at(self.pc_usize())
     }
 
-    pub fn dump_registers(&mut self) -> String {
-        let mut str = String::new();
-
-        for ii in 0..self.registers.len() {
-            str += std::format!("r{}: {}\n", ii, self.registers[ii]).as_str();
-        }
-
-        str += std::format!("index: {}\n", self.index).as_str();
-
-        str
+    fn opcode_at(&self, address: usize) -> u16 {
+        let size = self.memory.len();
+        (u16::from(self.memory[address % size]) << 8) | u16::from(self.memory[(address + 1) % size])
     }
 
-    // instruction(00E0): clear display
-    pub fn cls(&mut self) {
-        if self.debug_enabled {
-            println!("cls");
+    // whether the cpu is spinning in the common delay busy-wait loop (Fx07, 3x00, jump back to the Fx07)
+    // until the timer hits zero, every iteration only re-reads the timer so the frontend can skip ahead
+    // to the next timer tick instead of burning host cpu on it
+    pub fn is_waiting_on_delay(&self) -> bool {
+        if self.delay_timer == 0 {
+            return false;
         }
 
-        for ii in &mut self.display { *ii = 0; }
-
-        self.should_draw = true;
-        self.pc += 2;
+        // the loop may start up to two instructions back, wrapping around the start of memory like the pc does
+        let size = self.memory.len();
+        (0..3).map(|ii| (self.pc_usize() + size - ii * 2) % size).any(|start| {
+            match (decode(self.opcode_at(start)), decode(self.opcode_at(start + 2)), decode(self.opcode_at(start + 4))) {
+                (Opcode::GetDelay(x), Opcode::SeVal(y, 0), Opcode::Jmp(address)) => x == y && address as usize == start,
+                _ => false
+            }
+        })
     }
 
-    // instruction(00EE): return from subroutine
-    pub fn ret(&mut self) {
-        if self.debug_enabled {
-            println!("ret");
-        }
-
-        self.sp -= 1;
-        self.pc = self.stack[self.sp as usize] as usize;
-        self.pc += 2;
+    // executes one instruction, a fault halts the machine and is returned, a halted machine doesn't run anything
+    pub fn cycle(&mut self) -> Result<(), Ch8Error> {
+        self.execute().map(|_| ())
     }
 
-    // instruction(1xxx): jmp to xxx
-    pub fn jmp(&mut self, address: i32) {
-        if self.debug_enabled {
-            println!("jmp {}", address);
-        }
-
-        self.pc = address as usize;
+    // same as cycle but describes the instruction that ran, None when the machine is halted
+    // nothing is printed, so tracers can log it wherever they like
+    pub fn cycle_one(&mut self) -> Result<Option<DecodedInstr>, Ch8Error> {
+        Ok(self.execute()?.map(|(pc, opcode, instr)| DecodedInstr { pc, opcode, instr, mnemonic: disasm::mnemonic(opcode) }))
     }
 
-    // instruction(2xxx): call subroutine at xxx
-    pub fn call(&mut self, address: i32) {
-        if self.debug_enabled {
-            println!("call {}", address);
+    // runs the instruction at pc and returns its address, raw opcode and decoded form
+    fn execute(&mut self) -> Result<Option<(u16, u16, Opcode)>, Ch8Error> {
+        if self.halted {
+            return Ok(None);
         }
 
-        self.stack[self.sp as usize] = self.pc as u16;
-        self.sp += 1;
-        self.pc = address as usize;
-    }
+        // an instruction starting on the last byte of memory has no second byte to fetch
+        if self.pc_usize() + 1 >= self.memory.len() {
+            return self.fault(Ch8Error::PcOutOfRange(self.pc));
+        }
 
-    // instruction(3xyy): skip next instruction if register x equals value yy
-    pub fn se_val(&mut self, register: u8, value: u8) {
-        if self.debug_enabled {
-            println!("se_val r{}, {}", register, value);
+        let opcode = self.fetch();
+
+        let pc = self.pc;
+        let registers = if log_enabled!(Level::Trace) { self.registers.clone() } else { Vec::new() };
+
+        let instr = decode(opcode);
+        match instr {
+            Opcode::Cls => self.cls(),
+            Opcode::Ret => self.ret()?,
+            Opcode::ScrollDown(n) => self.scroll_down(n),
+            Opcode::ScrollRight => self.scroll_right(),
+            Opcode::ScrollLeft => self.scroll_left(),
+            Opcode::LoRes => self.set_resolution(false),
+            Opcode::HiRes => self.set_resolution(true),
+            Opcode::Plane(n) => self.select_planes(n),
+            Opcode::Sys(address) => self.sys(address)?,
+            Opcode::Jmp(address) => self.jmp(address),
+            Opcode::Call(address) => self.call(address)?,
+            Opcode::SeVal(x, value) => self.se_val(x, value),
+            Opcode::SneVal(x, value) => self.sne_val(x, value),
+            Opcode::SeReg(x, y) => self.se_reg(x, y),
+            Opcode::LdReg(x, value) => self.ld_reg(x, value),
+            Opcode::AddVal(x, value) => self.add_val(x, value),
+            Opcode::Copy(x, y) => self.copy(x, y),
+            Opcode::Or(x, y) => self.or(x, y),
+            Opcode::And(x, y) => self.and(x, y),
+            Opcode::Xor(x, y) => self.xor(x, y),
+            Opcode::AddReg(x, y) => self.add_reg(x, y),
+            Opcode::SubRegXY(x, y) => self.sub_regxy(x, y),
+            Opcode::ShiftR(x, y) => self.shift_r(x, y),
+            Opcode::SubRegYX(x, y) => self.sub_regyx(x, y),
+            Opcode::ShiftL(x, y) => self.shift_l(x, y),
+            Opcode::SneReg(x, y) => self.sne_reg(x, y),
+            Opcode::LdIndx(value) => self.ld_indx(value),
+            Opcode::JmpAdd(address) => self.jmpadd(address),
+            Opcode::RandAnd(x, value) => self.rand_and(x, value),
+            Opcode::DrawPixel(x, y, height) => self.draw_pixel(x, y, height),
+            Opcode::SeKp(x) => self.se_kp(x),
+            Opcode::SneKp(x) => self.sne_kp(x),
+            Opcode::GetDelay(x) => self.get_delay(x),
+            Opcode::WaitKey(x) => self.wait_key(x),
+            Opcode::SetDelay(x) => self.set_delay(x),
+            Opcode::SetSound(x) => self.set_sound(x),
+            Opcode::AddIndx(x) => self.add_indx(x),
+            Opcode::GetFontChr(x) => self.get_font_chr(x),
+            Opcode::EncodeSave(x) => self.encode_save(x),
+            Opcode::Save(x) => self.save(x),
+            Opcode::Load(x) => self.load(x),
+            Opcode::SaveFlags(x) => self.save_flags(x),
+            Opcode::LoadFlags(x) => self.load_flags(x),
+            Opcode::Unknown(opcode) => self.unknown(opcode)?
         }
 
-        if self.registers[register as usize] == value {
-            self.pc += 2;
+        if log_enabled!(Level::Trace) {
+            self.trace(pc as usize, opcode, &registers);
         }
 
-        self.pc += 2;
-    }
+        self.instructions += 1;
+        self.apply_queued_keys();
 
-    // instruction(4xyy): skip next instruction if register x does not equal value yy
-    pub fn sne_val(&mut self, register: u8, value: u8) {
-        if self.debug_enabled {
-            println!("sne_val r{}, {}", register, value);
+        if self.halt_on_rom_end && self.pc_usize() >= ROM_START + self.rom_len {
+            return self.fault(Ch8Error::PastRomEnd(self.pc));
         }
 
-        if self.registers[register as usize] != value {
-            self.pc += 2;
+        if !self.in_frame {
+            self.update_timers(Duration::from_secs(1) / self.instruction_rate);
         }
 
-        self.pc += 2;
+        Ok(Some((pc, opcode, instr)))
     }
 
-    // instruction(5xy0): skip next instruction if register x equals register y
-    pub fn se_reg(&mut self, register_x: u8, register_y: u8) {
-        if self.debug_enabled {
-            println!("se_reg r{}, r{}", register_x, register_y);
-        }
+    fn fault<T>(&mut self, error: Ch8Error) -> Result<T, Ch8Error> {
+        self.halted = true;
+        Err(error)
+    }
 
-        if self.registers[register_x as usize] == self.registers[register_y as usize] {
-            self.pc += 2;
+    // advances the timers by elapsed emulated time, ticking them once per 1/60th of a second
+    pub fn update_timers(&mut self, elapsed: Duration) {
+        let period = Duration::from_secs(1) / TIMER_RATE;
+        self.timer_accumulator += elapsed;
+
+        while self.timer_accumulator >= period {
+            self.timer_accumulator -= period;
+            self.tick_timers();
         }
+    }
 
-        self.pc += 2;
+    pub fn instruction_rate(&self) -> u32 {
+        self.instruction_rate
     }
 
-    // instruction(6xyy): set register x to byte yy
-    pub fn ld_reg(&mut self, register: u8, value: u8) {
-        if self.debug_enabled {
-            println!("ld_reg r{}, {}", register, value);
-        }
+    // every cycle counts as 1/rate of a second for the timers
+    pub fn set_instruction_rate(&mut self, rate: u32) {
+        self.instruction_rate = rate.max(1);
+    }
 
-        self.registers[register as usize] = value;
-        self.pc += 2;
+    // instructions to run per 60hz frame at the current rate, rounded to the nearest and at least one
+    pub fn cycles_per_frame(&self) -> u32 {
+        ((self.instruction_rate + TIMER_RATE / 2) / TIMER_RATE).max(1)
     }
 
-    // instruction(7xyy): adds yy to register x
-    pub fn add_val(&mut self, register: u8, value: u8) {
-        if self.debug_enabled {
-            println!("add_val r{}, {}", register, value);
+    // queues a press and release of key, applied once that many instructions have executed so the
+    // instruction after the press_at'th one sees the key down, events on the same instruction apply in queue order
+    pub fn queue_keypress(&mut self, key: u8, press_at_instruction: u64, release_at_instruction: u64) -> Result<(), Ch8Error> {
+        if key as usize >= self.kp_input.len() {
+            return Err(Ch8Error::InvalidKey(key));
         }
 
-        let val = self.registers[register as usize];
+        if release_at_instruction <= press_at_instruction {
+            return Err(Ch8Error::ReleaseBeforePress(key, press_at_instruction, release_at_instruction));
+        }
 
-        self.registers[register as usize] = val.wrapping_add(value);
-        self.pc += 2;
+        self.kp_queue.push((press_at_instruction, key as usize, true));
+        self.kp_queue.push((release_at_instruction, key as usize, false));
+        self.kp_queue.sort_by_key(|(at, _, _)| *at);
+        self.apply_queued_keys();
+
+        Ok(())
     }
 
-    // instruction(8xy0): copy value from register y to register x
-    pub fn copy(&mut self, register_x: u8, register_y: u8) {
-        if self.debug_enabled {
-            println!("copy r{}, r{}", register_x, register_y);
+    fn apply_queued_keys(&mut self) {
+        while !self.kp_queue.is_empty() && self.kp_queue[0].0 <= self.instructions {
+            let (_, key, pressed) = self.kp_queue.remove(0);
+            self.set_input(key as u8, pressed);
         }
+    }
 
-        self.registers[register_x as usize] = self.registers[register_y as usize];
-        self.pc += 2;
+    pub fn instructions(&self) -> u64 {
+        self.instructions
     }
 
-    // instruction(8xy1): bitwise or on register x using register y, set register F to 0
-    pub fn or(&mut self, register_x: u8, register_y: u8) {
-        if self.debug_enabled {
-            println!("or r{}, r{}", register_x, register_y);
+    // decrements the delay and sound timers by one, unless they are frozen for debugging
+    pub fn tick_timers(&mut self) {
+        if self.timers_frozen {
+            return;
         }
 
-        self.registers[register_x as usize] |= self.registers[register_y as usize];
-        // self.registers[0x0F] = 0;
-        self.pc += 2;
-    }
-
-    // instruction(8xy2): bitwise and on register x using register y, set register F to 0
-    pub fn and(&mut self, register_x: u8, register_y: u8) {
-        if self.debug_enabled {
-            println!("and r{}, r{}", register_x, register_y);
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
         }
 
-        self.registers[register_x as usize] &= self.registers[register_y as usize];
-        // self.registers[0x0F] = 0;
-        self.pc += 2;
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
     }
 
-    // instruction(8xy3): xor on register x using register y, set register F to 0
-    pub fn xor(&mut self, register_x: u8, register_y: u8) {
-        if self.debug_enabled {
-            println!("xor r{}, r{}", register_x, register_y);
-        }
+    pub fn freeze_timers(&mut self, frozen: bool) {
+        self.timers_frozen = frozen;
+    }
 
-        self.registers[register_x as usize] ^= self.registers[register_y as usize];
-        // self.registers[0x0F] = 0;
-        self.pc += 2;
+    pub fn timers_frozen(&self) -> bool {
+        self.timers_frozen
     }
 
-    // instruction(8xy4): adds register y to register x, set register F to 1 if operation wraps around, 0 if not
-    pub fn add_reg(&mut self, register_x: u8, register_y: u8) {
-        if self.debug_enabled {
-            println!("add_reg r{}, r{}", register_x, register_y);
+    fn trace(&self, pc: usize, opcode: u16, registers: &[u8]) {
+        if let Some(filter) = &self.trace_filter {
+            if !filter.matches(opcode) {
+                return;
+            }
         }
 
-        if self.registers[register_y as usize] > (0xFF - self.registers[register_x as usize]) {
-            self.registers[0x0F] = 1;
-        } else {
-            self.registers[0x0F] = 0;
+        match self.trace_format {
+            TraceFormat::Text => {
+                trace!("{}", disasm::mnemonic(opcode));
+            }
+            TraceFormat::Json => {
+                let entry = TraceEntry {
+                    pc,
+                    opcode,
+                    mnemonic: disasm::mnemonic(opcode),
+                    registers: registers.iter().zip(self.registers.iter()).enumerate()
+                        .filter(|(_, (old, new))| old != new)
+                        .map(|(ii, (old, new))| RegisterDelta { register: ii as u8, old: *old, new: *new })
+                        .collect()
+                };
+
+                trace!("{}", entry.to_json());
+            }
         }
+    }
 
-        let val = self.registers[register_x as usize];
-        self.registers[register_x as usize] = val.wrapping_add(self.registers[register_y as usize]);
-        self.pc += 2;
+    pub fn set_trace_format(&mut self, trace_format: TraceFormat) {
+        self.trace_format = trace_format;
     }
 
-    // instruction(8xy5): subtracts register y from register x, set register F to 1 if operation wraps around, 0 if not
-    pub fn sub_regxy(&mut self, register_x: u8, register_y: u8) {
-        if self.debug_enabled {
-            println!("sub_regxy r{}, r{}", register_x, register_y);
-        }
+    pub fn set_trace_filter(&mut self, trace_filter: Option<TraceFilter>) {
+        self.trace_filter = trace_filter;
+    }
 
-        if self.registers[register_y as usize] > self.registers[register_x as usize] {
-            self.registers[0x0F] = 0;
-        } else {
-            self.registers[0x0F] = 1;
-        }
+    // reseeds the random number generator behind Cxnn so runs are reproducible
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
 
-        let val = self.registers[register_x as usize];
-        self.registers[register_x as usize] = val.wrapping_sub(self.registers[register_y as usize]);
-        self.pc += 2;
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
     }
 
-    // instruction(8xy6): shift register right by 1, register F is set to the lsb of register before shifting 
-    pub fn shift_r(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("shift_r r{}", register);
-        }
+    pub fn set_halt_on_rom_end(&mut self, halt_on_rom_end: bool) {
+        self.halt_on_rom_end = halt_on_rom_end;
+    }
 
-        self.registers[0x0F] = self.registers[register as usize] & 0x1;
-        self.registers[register as usize] >>= 1;
-        self.pc += 2;
+    pub fn is_halted(&self) -> bool {
+        self.halted
     }
 
-    // instruction(8xy7): sets register x to register y minus register x, set register F to 1 if operation wraps around, 0 if not
-    pub fn sub_regyx(&mut self, register_x: u8, register_y: u8) {
-        if self.debug_enabled {
-            println!("sub_regyx r{}, r{}", register_x, register_y);
-        }
+    // runs up to n instructions without a window, stopping early if the machine halts
+    // returns how many instructions were actually executed
+    pub fn run_cycles(&mut self, n: usize) -> Result<usize, Ch8Error> {
+        for ii in 0..n {
+            if self.halted {
+                return Ok(ii);
+            }
 
-        if self.registers[register_x as usize] > self.registers[register_y as usize] {
-            self.registers[0x0F] = 0;
-        } else {
-            self.registers[0x0F] = 1;
+            self.cycle()?;
         }
 
-        let val = self.registers[register_y as usize];
-        self.registers[register_x as usize] = val.wrapping_sub(self.registers[register_x as usize]);
-        self.pc += 2;
+        Ok(n)
     }
 
-    // instruction(8xyE): shift register left by 1, register F is set to the msb of register before shifting 
-    pub fn shift_l(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("shift_l r{}", register);
-        }
+    // runs one 60hz frame, cycles_per_frame instructions followed by exactly one timer tick
+    // returns how many instructions were executed, fewer than cycles_per_frame only if the machine halted
+    pub fn run_frame(&mut self) -> Result<usize, Ch8Error> {
+        self.in_frame = true;
+        let executed = self.run_cycles(self.cycles_per_frame() as usize);
+        self.in_frame = false;
 
-        self.registers[0x0F] = self.registers[register as usize] >> 7;
-        self.registers[register as usize] <<= 1;
-        self.pc += 2;
+        self.tick_timers();
+
+        executed
     }
 
-    // instruction(9xy0): skip next instruction if register x does not equal register y
-    pub fn sne_reg(&mut self, register_x: u8, register_y: u8) {
-        if self.debug_enabled {
-            println!("sne_reg r{}, r{}", register_x, register_y);
-        }
+    // runs until an instruction that changes the display (00E0, 00Cn, 00FB, 00FC, 00FE, 00FF or Dxyn) has executed
+    // gives up after a second's worth of instructions so a rom that never draws can't hang the caller
+    // returns how many instructions were executed, including the drawing one
+    pub fn run_until_draw(&mut self) -> Result<usize, Ch8Error> {
+        for ii in 0..self.instruction_rate as usize {
+            if self.halted {
+                return Ok(ii);
+            }
 
-        if self.registers[register_x as usize] != self.registers[register_y as usize] {
-            self.pc += 2;
-        }
+            // an out of range pc can't be fetched here, cycle faults on it instead
+            let draws = self.pc_usize() + 1 < self.memory.len() && matches!(decode(self.fetch()),
+                Opcode::Cls | Opcode::ScrollDown(_) | Opcode::ScrollRight | Opcode::ScrollLeft | Opcode::LoRes | Opcode::HiRes
+                | Opcode::DrawPixel(..));
 
-        self.pc += 2;
-    }
+            self.cycle()?;
 
-    // instruction(Axxx): set index to xxx
-    pub fn ld_indx(&mut self, value: i32) {
-        if self.debug_enabled {
-            println!("ld_indx {}", value);
+            if draws {
+                return Ok(ii + 1);
+            }
         }
 
-        self.index = value as u16;
-        self.pc += 2;
+        Ok(self.instruction_rate as usize)
     }
 
-    // instruction(Bxxx): jump to address xxx plus value of register 0
-    pub fn jmpadd(&mut self, address: i32) {
-        if self.debug_enabled {
-            println!("jmpadd {}", address);
+    pub fn add_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.contains(&address) {
+            self.breakpoints.push(address);
         }
-        
-        self.pc = address as usize;
-        self.pc += self.registers[0 as usize] as usize;
     }
 
-    // instruction(Cxyy): performs and operation on random byte and value yy, stores it into register x
-    pub fn rand_and(&mut self, register: u8, value: u8) {
-        if self.debug_enabled {
-            println!("rand_and r{}, {}", register, value);
-        }
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.breakpoints.retain(|breakpoint| *breakpoint != address);
+    }
 
-        self.registers[register as usize] = (self.rng.gen_range(0..0xFF)) & value;                
-        self.pc += 2;
+    pub fn breakpoints(&self) -> &[u16] {
+        &self.breakpoints
     }
 
-    // instruction(Dxyz): set pixel at x/y coord to height z
-    pub fn draw_pixel(&mut self, register_x: u8, register_y: u8, height: i32) {
-        if self.debug_enabled {
-            println!("draw_pixel r{}, r{}, {}", register_x, register_y, height);
+    // records every write the rom makes to address (Fx33 and Fx55), even when the value doesn't change
+    // writes through set_memory come from the host and aren't recorded
+    pub fn add_watchpoint(&mut self, address: u16) {
+        if !self.watchpoints.contains(&address) {
+            self.watchpoints.push(address);
         }
+    }
+
+    pub fn remove_watchpoint(&mut self, address: u16) {
+        self.watchpoints.retain(|watchpoint| *watchpoint != address);
+    }
 
-        let pixel_x = (self.registers[register_x as usize] as usize % VIDEO_WIDTH) as i32;
-        let pixel_y = (self.registers[register_y as usize] as usize % VIDEO_HEIGHT) as i32;
+    // the watched writes since the last call, oldest first
+    pub fn take_watch_hits(&mut self) -> Vec<WatchHit> {
+        std::mem::take(&mut self.watch_hits)
+    }
 
-        self.registers[0x0F as usize] = 0;
+    // memory write made by an instruction, checked against the watchpoints
+    fn write_memory(&mut self, address: usize, value: u8) {
+        if self.watchpoints.contains(&(address as u16)) {
+            self.watch_hits.push(WatchHit { pc: self.pc, address: address as u16, old: self.memory[address], new: value });
+        }
 
-        for row in 0..height {
-            let pixel = self.memory[(self.index as i32 + row) as usize];
+        self.memory[address] = value;
+    }
 
-            for column in 0..8 {
-                if (pixel & (0x80 >> column)) != 0 {
-                    let indx = (((pixel_y + row) * (VIDEO_WIDTH as i32) + (pixel_x + column)) % ((VIDEO_WIDTH * VIDEO_HEIGHT) as i32)) as usize;
+    // runs until the pc lands on a breakpoint and returns it, at least one instruction runs so continuing from a
+    // breakpoint doesn't stop on it again right away, gives up with None after max_cycles or when the machine halts
+    pub fn run_until_breakpoint(&mut self, max_cycles: usize) -> Result<Option<u16>, Ch8Error> {
+        for _ in 0..max_cycles {
+            if self.halted {
+                break;
+            }
 
-                    if self.display[indx] == 1 {
-                        self.registers[0x0F] = 1;
-                    }
+            self.cycle()?;
 
-                    self.display[indx] ^= 1;
-                }
+            if self.breakpoints.contains(&self.pc) {
+                return Ok(Some(self.pc));
             }
         }
 
-        self.should_draw = true;
-        self.pc += 2;
+        Ok(None)
     }
 
-    // instruction(Ex9E): skip next instruction if key with value of register is pressed 
-    pub fn se_kp(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("se_kp r{} -> idx{}", register, self.registers[register as usize]);
+    // executes the next instruction, a call (2xxx) is run until the subroutine returns to the instruction after it
+    // gives up after max_cycles so a subroutine that never returns can't hang the caller, returns whether it returned
+    pub fn step_over(&mut self, max_cycles: usize) -> Result<bool, Ch8Error> {
+        if !matches!(decode(self.fetch()), Opcode::Call(_)) {
+            self.cycle()?;
+            return Ok(true);
         }
 
-        let keynum = self.registers[register as usize];
+        let return_address = (self.pc + 2) & PC_MASK;
+        let sp = self.sp;
 
-        if self.kp_input[keynum as usize] >= 1 {
-            self.pc += 2;
+        for _ in 0..max_cycles {
+            self.cycle()?;
+
+            if (self.pc == return_address && self.sp == sp) || self.halted {
+                return Ok(true);
+            }
         }
 
-        self.pc += 2;
+        Ok(false)
     }
 
-    // instruction(ExA1): skip next instruction if key with value of register is not pressed
-    pub fn sne_kp(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("sne_kp r{} -> idx{}", register, self.registers[register as usize]);
+    pub fn snapshot(&self) -> Chip8State {
+        Chip8State {
+            registers: self.registers.clone(),
+            memory: self.memory.clone(),
+            stack: self.stack.clone(),
+            display: self.display.clone(),
+            hires: self.hires,
+            planes: self.planes,
+            kp_input: self.kp_input.clone(),
+            index: self.index,
+            pc: self.pc,
+            sp: self.sp,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer
         }
+    }
 
-        let keynum = self.registers[register as usize];
+    pub fn restore(&mut self, state: &Chip8State) {
+        self.registers.copy_from_slice(&state.registers);
+        self.memory.copy_from_slice(&state.memory);
+        self.stack.copy_from_slice(&state.stack);
+        self.display.copy_from_slice(&state.display);
+        self.hires = state.hires;
+        self.planes = state.planes;
+        self.kp_input.copy_from_slice(&state.kp_input);
+        self.index = state.index;
+        self.set_pc(state.pc);
+        self.sp = state.sp;
+        self.delay_timer = state.delay_timer;
+        self.sound_timer = state.sound_timer;
+        self.should_draw = true;
+    }
 
-        if self.kp_input[keynum as usize] == 0 {
-            self.pc += 2;
-        }
+    // writes the machine state in the tagged save state format, see savestate
+    pub fn save_state_to_file(&self, path: &Path) -> Result<()> {
+        fs::write(path, savestate::encode(&self.snapshot())).context("failed to write save state")
+    }
 
-        self.pc += 2;
+    pub fn load_state_from_file(&mut self, path: &Path) -> Result<()> {
+        let bytes = fs::read(path).context("failed to read save state")?;
+
+        let mut state = self.snapshot();
+        savestate::decode(&bytes, &mut state)?;
+        self.restore(&state);
+
+        Ok(())
     }
 
-    // instruction(Fx07): sets register x to value of delay timer
-    pub fn get_delay(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("get_delay r{}", register);
-        }
+    pub fn rpl_flags(&self) -> [u8; 8] {
+        self.rpl_flags
+    }
 
-        self.registers[register as usize] = self.delay_timer;
-        self.pc += 2;
+    pub fn set_rpl_flags(&mut self, flags: [u8; 8]) {
+        self.rpl_flags = flags;
     }
 
-    // instruction(Fx0A): awaits key press and stores it into register x
-    pub fn wait_key(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("wait_key r{}", register);
-        }
-        
-        let mut key_pressed = false;
-        for ii in 0..self.kp_input.len() {
-            if self.kp_input[ii] != 0 {
-                key_pressed = true;
-                self.registers[register as usize] = ii as u8;
-            }
-        }
+    // the flags are stored as 8 raw bytes so games can keep their high scores between runs
+    pub fn save_rpl_flags(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.rpl_flags).context("failed to write rpl flags")
+    }
 
-        if key_pressed {
-            self.pc += 2;
+    pub fn load_rpl_flags(&mut self, path: &Path) -> Result<()> {
+        let bytes = fs::read(path).context("failed to read rpl flags")?;
+
+        if bytes.len() != self.rpl_flags.len() {
+            bail!("rpl flags file has {} bytes, expected {}", bytes.len(), self.rpl_flags.len());
         }
+
+        self.rpl_flags.copy_from_slice(&bytes);
+
+        Ok(())
     }
 
-    // instruction(Fx15): sets delay timer to value of register x 
-    pub fn set_delay(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("set_delay r{}", register);
+    pub fn diff(&self, other: &Chip8) -> StateDiff {
+        StateDiff {
+            registers: diff_indices(&self.registers, &other.registers),
+            memory: diff_indices(&self.memory, &other.memory),
+            stack: diff_indices(&self.stack, &other.stack),
+            display: diff_indices(&self.display, &other.display)
         }
-
-        self.delay_timer = self.registers[register as usize];
-        self.pc += 2;
     }
 
-    // instruction(Fx18): sets sound timer to value of register x 
-    pub fn set_sound(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("set_sound r{}", register);
+    // hands the display to the renderer if it changed since the last call, returns whether a frame was presented
+    pub fn present(&mut self, renderer: &mut dyn Renderer) -> bool {
+        if !self.should_draw {
+            return false;
         }
 
-        self.sound_timer = self.registers[register as usize];
-        self.pc += 2;
+        self.should_draw = false;
+        renderer.present(self.display());
+
+        true
     }
 
-    // instruction(Fx1E): adds register x to index, set register F to 1 if operation wraps around, 0 if not
-    pub fn add_indx(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("add_indx r{}", register);
-        }
+    // width and height of the display in pixels
+    pub fn display_dimensions(&self) -> (usize, usize) {
+        if self.hires { (HIRES_VIDEO_WIDTH, HIRES_VIDEO_HEIGHT) } else { (VIDEO_WIDTH, VIDEO_HEIGHT) }
+    }
 
-        if self.registers[register as usize] as i32 + self.index as i32 > 0xFFF {
-            self.registers[0x0F] = 1;
-        } else {
-            self.registers[0x0F] = 0;
-        }
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
 
-        self.index = self.index.wrapping_add(self.registers[register as usize] as u16);
-        self.pc += 2;
+    // palette index of a pixel, 0 is the background, 1 plane 1, 2 plane 2 and 3 both planes
+    pub fn get_display(&self, index: usize) -> u32 {
+        self.display[index]
     }
 
-    // instruction(Fx29): gets font character for digit stored in register and sets index to it
-    pub fn get_font_chr(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("get_font_chr r{}", register);
-        }
+    pub fn planes(&self) -> u8 {
+        self.planes
+    }
 
-        let val = self.registers[register as usize];
-        self.index = (0x50 + (5 * val)) as u16;
-        self.pc += 2;
+    // pixels of the active resolution, row by row
+    pub fn display(&self) -> &[u32] {
+        let (width, height) = self.display_dimensions();
+        &self.display[..width * height]
     }
 
-    // instruction(Fx33): saves most significant bits of register into memory at index
-    pub fn encode_save(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("encode_save r{}", register);
+    // fnv-1a hash of the lit pixels, stable across rust versions unlike DefaultHasher, for comparing against known good screens
+    pub fn display_hash(&self) -> u64 {
+        self.display().iter().fold(0xcbf2_9ce4_8422_2325, |hash, pixel| (hash ^ (*pixel != 0) as u64).wrapping_mul(0x100_0000_01b3))
+    }
+
+    // the display as rgba bytes in the default colors, every pixel blown up to a scale x scale square
+    pub fn display_rgba(&self, scale: usize) -> Vec<u8> {
+        let (width, height) = self.display_dimensions();
+        let mut argb = vec![0; width * height];
+        render_argb(self.display(), &[BACKGROUND, FOREGROUND, PLANE_2, PLANES_BOTH], &mut argb);
+
+        let mut rgba = Vec::with_capacity(width * height * scale * scale * 4);
+        for row in argb.chunks(width) {
+            for _ in 0..scale {
+                for pixel in row {
+                    let [a, r, g, b] = pixel.to_be_bytes();
+                    for _ in 0..scale {
+                        rgba.extend_from_slice(&[r, g, b, a]);
+                    }
+                }
+            }
         }
 
-        let value = self.registers[register as usize];
-        self.memory[(self.index as usize)] = (value / 100) as u8;
-        self.memory[((self.index + 1) as usize)] = ((value / 10) % 10) as u8;
-        self.memory[((self.index + 2) as usize)] = ((value % 100) % 10) as u8;
-        self.pc += 2;
+        rgba
     }
 
-    // instruction(Fx55): save register 0 until register x to memory starting at index
-    pub fn save(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("save r{}", register);
-        }
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
 
-        for ii in 0..register + 1 {
-            self.memory[(self.index + ii as u16) as usize] = self.registers[ii as usize];
-        }
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
 
-        self.index = self.index.wrapping_add((register + 1) as u16);
-        self.pc += 2;
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
     }
 
-    // instruction(Fx65): load register 0 until register x to memory starting at index
-    pub fn load(&mut self, register: u8) {
-        if self.debug_enabled {
-            println!("load r{}", register);
-        }
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
 
-        for ii in 0..register + 1 {
-            self.registers[ii as usize] = self.memory[(self.index + ii as u16) as usize];
-        }
+    // the frontend plays a tone while the sound timer is active
+    pub fn is_beeping(&self) -> bool {
+        self.sound_timer > 0
+    }
 
-        self.index = self.index.wrapping_add((register + 1) as u16);
-        self.pc += 2;
+    pub fn get_pc(&self) -> u16 {
+        self.pc
     }
-}
 
-// shift helpers
-pub fn shift_u8(value: i32, bits: i32, binary_and: i32) -> u8 {
-    ((value & binary_and) >> bits) as u8
-}
+    // the pc as an index into memory
+    pub fn pc_usize(&self) -> usize {
+        self.pc as usize
+    }
 
-pub fn shift_i32(value: i32, bits: i32, binary_and: i32) -> i32 {
-    (value & binary_and) >> bits
+    fn set_pc(&mut self, pc: u16) {
+        self.pc = pc & PC_MASK;
+    }
+
+    pub fn get_index(&self) -> u16 {
+        self.index
+    }
+
+    pub fn get_register(&self, register: usize) -> u8 {
+        self.registers[register]
+    }
+
+    // V0 - VF
+    pub fn registers(&self) -> &[u8] {
+        &self.registers
+    }
+
+    // all 16 stack slots, only the first sp of them hold pending calls
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn sp(&self) -> u8 {
+        self.sp
+    }
+
+    pub fn memory_size(&self) -> usize {
+        self.memory.len()
+    }
+
+    // resizes memory for experimental roms, clamped between the standard 4k and what the index register can address
+    // the pc stays 12 bits, memory above 0xFFF is only reachable through the index register
+    // memory can't shrink below a loaded rom, the rom would be cut off
+    pub fn set_memory_size(&mut self, size: usize) -> Result<(), Ch8Error> {
+        let size = size.max(MEMORY_SIZE).min(MAX_MEMORY_SIZE);
+        if size < ROM_START + self.rom_len {
+            return Err(Ch8Error::RomTooLarge(self.rom_len));
+        }
+
+        self.memory.resize(size, 0);
+
+        Ok(())
+    }
+
+    pub fn get_memory(&self, address: usize) -> u8 {
+        self.memory[address]
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    // the byte at address, None past the end of memory
+    pub fn peek(&self, address: u16) -> Option<u8> {
+        self.memory.get(address as usize).copied()
+    }
+
+    pub fn set_memory(&mut self, address: usize, value: u8) {
+        self.memory[address] = value;
+    }
+
+    // whether chip8 key 0x0 - 0xF is held, keys above 0xF are never held
+    pub fn get_input(&self, key: u8) -> bool {
+        self.kp_input.get(key as usize).is_some_and(|state| *state != 0)
+    }
+
+    // presses or releases chip8 key 0x0 - 0xF, numbered after the hex keypad digits which the cosmac vip lays out as
+    // 1 2 3 C / 4 5 6 D / 7 8 9 E / A 0 B F, keys above 0xF are ignored
+    pub fn set_input(&mut self, key: u8, pressed: bool) {
+        self.key_event(key, pressed);
+    }
+
+    // records a key going down or up, a press is latched until Ex9E or ExA1 reads that key even if it is
+    // released before then, and a release (down then up) is what completes an Fx0A wait
+    pub fn key_event(&mut self, key: u8, pressed: bool) {
+        let key = key as usize;
+        if key >= self.kp_input.len() {
+            return;
+        }
+
+        if pressed {
+            self.kp_latched[key] = true;
+        } else if self.kp_input[key] != 0 {
+            self.kp_released[key] = true;
+        }
+
+        self.kp_input[key] = pressed as u32;
+    }
+
+    // V0 - VF four to a row followed by I, PC, SP and the timers, all in hex
+    pub fn dump_registers(&self) -> String {
+        let mut str = String::new();
+
+        for (row, registers) in self.registers.chunks(4).enumerate() {
+            let cells: Vec<String> = registers.iter().enumerate()
+                .map(|(ii, value)| std::format!("V{:X}: {:02X}", row * 4 + ii, value))
+                .collect();
+            str += std::format!("{}\n", cells.join("  ")).as_str();
+        }
+
+        str += std::format!("I:  {:04X}  PC: {:04X}  SP: {:02X}\n", self.index, self.pc, self.sp).as_str();
+        str += std::format!("DT: {:02X}    ST: {:02X}\n", self.delay_timer, self.sound_timer).as_str();
+
+        str
+    }
+
+    // addresses of the pending calls from the bottom of the stack up, the last line is the most recent call
+    pub fn dump_stack(&self) -> String {
+        let mut str = String::new();
+
+        for (ii, address) in self.stack[..self.sp as usize].iter().enumerate() {
+            str += std::format!("{:X}: {:04X}\n", ii, address).as_str();
+        }
+
+        if str.is_empty() {
+            str += "stack is empty\n";
+        }
+
+        str
+    }
+
+    // the 16 font glyphs as they are in memory, rendered side by side as ascii art
+    pub fn dump_fontset(&self) -> String {
+        let mut str = String::new();
+
+        for glyph in 0..16 {
+            str += std::format!("{:<5}", std::format!("{:X}", glyph)).as_str();
+        }
+        str += "\n";
+
+        for row in 0..5 {
+            for glyph in 0..16 {
+                let byte = self.memory[FONT_START + glyph * 5 + row];
+                for bit in 0..4 {
+                    str += if byte & (0x80 >> bit) != 0 { "#" } else { "." };
+                }
+                str += " ";
+            }
+            str += "\n";
+        }
+
+        str
+    }
+
+    // bytes of the loaded rom as they currently are in memory
+    pub fn rom_bytes(&self) -> &[u8] {
+        &self.memory[ROM_START..ROM_START + self.rom_len]
+    }
+
+    pub fn memory_map(&self) -> String {
+        let mut str = String::new();
+
+        str += std::format!("load address: {:#05X}\n", ROM_START).as_str();
+        str += std::format!("rom size: {} bytes\n", self.rom_len).as_str();
+        str += std::format!("end address: {:#05X}\n", ROM_START + self.rom_len).as_str();
+        str += std::format!("free memory: {} bytes\n", self.memory.len() - ROM_START - self.rom_len).as_str();
+
+        str
+    }
+
+    // unknown instruction, skipped so malformed roms can't spin on the same address, or halts in strict mode
+    fn unknown(&mut self, opcode: u16) -> Result<(), Ch8Error> {
+        if self.strict {
+            return self.fault(Ch8Error::InvalidOpcode(opcode));
+        }
+
+        warn!("unknown instruction {:#06X} at {:#05X}", opcode, self.pc);
+        self.set_pc(self.pc + 2);
+
+        Ok(())
+    }
+
+    // instruction(0xxx): call machine code routine at xxx, not supported so it is skipped or halts in strict mode
+    pub fn sys(&mut self, address: u16) -> Result<(), Ch8Error> {
+        if self.strict {
+            return self.fault(Ch8Error::InvalidOpcode(address));
+        }
+
+        self.set_pc(self.pc + 2);
+
+        Ok(())
+    }
+
+    // instruction(00E0): clear the selected planes of the display
+    pub fn cls(&mut self) {
+        let (width, height) = self.display_dimensions();
+        self.clear_region(0, 0, width, height);
+
+        self.should_draw = true;
+        self.set_pc(self.pc + 2);
+    }
+
+    // blanks the selected planes in a w by h rectangle of the display, clipped to the active resolution
+    fn clear_region(&mut self, x: usize, y: usize, w: usize, h: usize) {
+        let (width, height) = self.display_dimensions();
+        let mask = self.planes as u32;
+
+        for row in y..(y + h).min(height) {
+            for column in x..(x + w).min(width) {
+                self.display[row * width + column] &= !mask;
+            }
+        }
+    }
+
+    // moves the selected planes by dx/dy pixels, pixels shifted off the edge are lost and the vacated area is cleared
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = self.display_dimensions();
+        let mask = self.planes as u32;
+        let previous = self.display.clone();
+
+        for row in 0..height as isize {
+            for column in 0..width as isize {
+                let (from_x, from_y) = (column - dx, row - dy);
+
+                if from_x >= 0 && from_x < width as isize && from_y >= 0 && from_y < height as isize {
+                    let indx = row as usize * width + column as usize;
+                    self.display[indx] = (self.display[indx] & !mask) | (previous[from_y as usize * width + from_x as usize] & mask);
+                }
+            }
+        }
+
+        let (rows, columns) = (dy.unsigned_abs().min(height), dx.unsigned_abs().min(width));
+        self.clear_region(0, if dy < 0 { height - rows } else { 0 }, width, rows);
+        self.clear_region(if dx < 0 { width - columns } else { 0 }, 0, columns, height);
+
+        self.should_draw = true;
+    }
+
+    // instruction(00Cn): scroll the display down n pixels
+    pub fn scroll_down(&mut self, n: u8) {
+        self.scroll(0, n as isize);
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(00FB): scroll the display right 4 pixels
+    pub fn scroll_right(&mut self) {
+        self.scroll(4, 0);
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(00FC): scroll the display left 4 pixels
+    pub fn scroll_left(&mut self) {
+        self.scroll(-4, 0);
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(00FE/00FF): switch to 64x32 (00FE) or 128x64 (00FF), every plane is cleared either way
+    pub fn set_resolution(&mut self, hires: bool) {
+        self.hires = hires;
+        for ii in &mut self.display { *ii = 0; }
+
+        self.should_draw = true;
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Fn01): select the xo-chip planes that drawing, clearing and scrolling apply to, n is a mask of planes 1 and 2
+    pub fn select_planes(&mut self, planes: u8) {
+        self.planes = planes & 0x3;
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(00EE): return from subroutine
+    pub fn ret(&mut self) -> Result<(), Ch8Error> {
+        if self.sp == 0 {
+            return self.fault(Ch8Error::StackUnderflow);
+        }
+
+        self.sp -= 1;
+        self.set_pc(self.stack[self.sp as usize] + 2);
+
+        Ok(())
+    }
+
+    // instruction(1xxx): jmp to xxx
+    pub fn jmp(&mut self, address: u16) {
+        self.set_pc(address);
+    }
+
+    // instruction(2xxx): call subroutine at xxx
+    pub fn call(&mut self, address: u16) -> Result<(), Ch8Error> {
+        if self.sp as usize >= self.stack.len() {
+            return self.fault(Ch8Error::StackOverflow);
+        }
+
+        self.stack[self.sp as usize] = self.pc;
+        self.sp += 1;
+        self.set_pc(address);
+
+        Ok(())
+    }
+
+    // instruction(3xyy): skip next instruction if register x equals value yy
+    pub fn se_val(&mut self, register: u8, value: u8) {
+        if self.registers[register as usize] == value {
+            self.set_pc(self.pc + 2);
+        }
+
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(4xyy): skip next instruction if register x does not equal value yy
+    pub fn sne_val(&mut self, register: u8, value: u8) {
+        if self.registers[register as usize] != value {
+            self.set_pc(self.pc + 2);
+        }
+
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(5xy0): skip next instruction if register x equals register y
+    pub fn se_reg(&mut self, register_x: u8, register_y: u8) {
+        if self.registers[register_x as usize] == self.registers[register_y as usize] {
+            self.set_pc(self.pc + 2);
+        }
+
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(6xyy): set register x to byte yy
+    pub fn ld_reg(&mut self, register: u8, value: u8) {
+        self.registers[register as usize] = value;
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(7xyy): adds yy to register x, wraps around on overflow without setting register F (unlike 8xy4)
+    pub fn add_val(&mut self, register: u8, value: u8) {
+        let val = self.registers[register as usize];
+
+        self.registers[register as usize] = val.wrapping_add(value);
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(8xy0): copy value from register y to register x
+    pub fn copy(&mut self, register_x: u8, register_y: u8) {
+        self.registers[register_x as usize] = self.registers[register_y as usize];
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(8xy1): bitwise or on register x using register y, set register F to 0
+    pub fn or(&mut self, register_x: u8, register_y: u8) {
+        self.registers[register_x as usize] |= self.registers[register_y as usize];
+        // self.registers[0x0F] = 0;
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(8xy2): bitwise and on register x using register y, set register F to 0
+    pub fn and(&mut self, register_x: u8, register_y: u8) {
+        self.registers[register_x as usize] &= self.registers[register_y as usize];
+        // self.registers[0x0F] = 0;
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(8xy3): xor on register x using register y, set register F to 0
+    pub fn xor(&mut self, register_x: u8, register_y: u8) {
+        self.registers[register_x as usize] ^= self.registers[register_y as usize];
+        // self.registers[0x0F] = 0;
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(8xy4): adds register y to register x, set register F to 1 if operation wraps around, 0 if not
+    // like every flag setting instruction the flag is written after the result, so it wins when x is register F
+    pub fn add_reg(&mut self, register_x: u8, register_y: u8) {
+        let (result, carry) = self.registers[register_x as usize].overflowing_add(self.registers[register_y as usize]);
+
+        self.registers[register_x as usize] = result;
+        self.registers[0x0F] = carry as u8;
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(8xy5): subtracts register y from register x, set register F to 0 if operation wraps around (borrow), 1 if not
+    pub fn sub_regxy(&mut self, register_x: u8, register_y: u8) {
+        let (result, borrow) = self.registers[register_x as usize].overflowing_sub(self.registers[register_y as usize]);
+
+        self.registers[register_x as usize] = result;
+        self.registers[0x0F] = !borrow as u8;
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(8xy6): shift register right by 1, register F is set to the lsb of register before shifting 
+    // shifts register y into register x instead when the shift quirk is enabled
+    pub fn shift_r(&mut self, register: u8, register_y: u8) {
+        let value = self.registers[if self.quirks.shift_uses_vy { register_y } else { register } as usize];
+        self.set_shift_result(register, value >> 1, value & 0x1);
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(8xy7): sets register x to register y minus register x, set register F to 0 if operation wraps around (borrow), 1 if not
+    pub fn sub_regyx(&mut self, register_x: u8, register_y: u8) {
+        let (result, borrow) = self.registers[register_y as usize].overflowing_sub(self.registers[register_x as usize]);
+
+        self.registers[register_x as usize] = result;
+        self.registers[0x0F] = !borrow as u8;
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(8xyE): shift register left by 1, register F is set to the msb of register before shifting 
+    // shifts register y into register x instead when the shift quirk is enabled
+    pub fn shift_l(&mut self, register: u8, register_y: u8) {
+        let value = self.registers[if self.quirks.shift_uses_vy { register_y } else { register } as usize];
+        self.set_shift_result(register, value << 1, value >> 7);
+        self.set_pc(self.pc + 2);
+    }
+
+    // the flag is written after the result, so shifting register F itself leaves the shifted out bit
+    // unless the quirk asks to keep the shifted value instead
+    fn set_shift_result(&mut self, register: u8, result: u8, flag: u8) {
+        self.registers[register as usize] = result;
+
+        if register != 0x0F || !self.quirks.shift_vf_keeps_result {
+            self.registers[0x0F] = flag;
+        }
+    }
+
+    // instruction(9xy0): skip next instruction if register x does not equal register y
+    pub fn sne_reg(&mut self, register_x: u8, register_y: u8) {
+        if self.registers[register_x as usize] != self.registers[register_y as usize] {
+            self.set_pc(self.pc + 2);
+        }
+
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Axxx): set index to xxx
+    pub fn ld_indx(&mut self, value: u16) {
+        self.index = value;
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Bxxx): jump to address xxx plus value of register 0, or register x when the jump quirk is enabled
+    pub fn jmpadd(&mut self, address: u16) {
+        let register = if self.quirks.jump_uses_vx { (address >> 8) & 0x0F } else { 0 };
+
+        self.set_pc(address + self.registers[register as usize] as u16);
+    }
+
+    // instruction(Cxyy): performs and operation on random byte and value yy, stores it into register x
+    pub fn rand_and(&mut self, register: u8, value: u8) {
+        self.registers[register as usize] = self.rng.gen::<u8>() & value;                
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Dxyz): set pixel at x/y coord to height z
+    // sprite rows are only read from memory at index, so drawing straight from the font region works like any other address
+    // in hi-res mode a height of 0 draws a 16x16 sprite of two bytes per row
+    // every selected plane gets its own sprite, the one for plane 2 follows the one for plane 1 in memory
+    pub fn draw_pixel(&mut self, register_x: u8, register_y: u8, height: u8) {
+        let (width, video_height) = self.display_dimensions();
+        let (sprite_width, sprite_height) = if self.hires && height == 0 { (16, 16) } else { (8, height as usize) };
+        let row_bytes = sprite_width / 8;
+
+        let pixel_x = self.registers[register_x as usize] as usize % width;
+        let pixel_y = self.registers[register_y as usize] as usize % video_height;
+
+        self.registers[0x0F] = 0;
+        let mut collided_rows = 0;
+        let mut offset = 0;
+        let planes = self.planes as u32;
+
+        for plane in [1u32, 2].iter().filter(|plane| planes & **plane != 0) {
+            for row in 0..sprite_height {
+                let pixel = (0..row_bytes).fold(0u16, |bits, byte| bits << 8 | self.memory[self.indexed(offset + row * row_bytes + byte)] as u16);
+                let mut collided = pixel_y + row >= video_height; // wrapped off the bottom
+
+                for column in 0..sprite_width {
+                    if (pixel & (1 << (sprite_width - 1 - column))) != 0 {
+                        let x = (pixel_x + column) % width;
+                        let y = (pixel_y + row) % video_height;
+                        let indx = y * width + x;
+
+                        if self.display[indx] & plane != 0 {
+                            self.registers[0x0F] = 1;
+                            collided = true;
+                        }
+
+                        self.display[indx] ^= plane;
+                    }
+                }
+
+                if collided {
+                    collided_rows += 1;
+                }
+            }
+
+            offset += sprite_height * row_bytes;
+        }
+
+        if self.quirks.schip_collision_count && self.hires {
+            self.registers[0x0F] = collided_rows;
+        }
+
+        self.should_draw = true;
+        self.set_pc(self.pc + 2);
+    }
+
+    // whether a key is held or was pressed since it was last read, reading it clears the latch
+    // only the low nibble of the key selects a key, like the cosmac vip's keypad decoder
+    fn key_down(&mut self, key: usize) -> bool {
+        let key = key & 0xF;
+        let down = self.kp_input[key] != 0 || self.kp_latched[key];
+        self.kp_latched[key] = false;
+
+        down
+    }
+
+    // instruction(Ex9E): skip next instruction if key with value of register is pressed 
+    pub fn se_kp(&mut self, register: u8) {
+        let keynum = self.registers[register as usize];
+
+        if self.key_down(keynum as usize) {
+            self.set_pc(self.pc + 2);
+        }
+
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(ExA1): skip next instruction if key with value of register is not pressed
+    pub fn sne_kp(&mut self, register: u8) {
+        let keynum = self.registers[register as usize];
+
+        if !self.key_down(keynum as usize) {
+            self.set_pc(self.pc + 2);
+        }
+
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Fx07): sets register x to value of delay timer
+    // the timers only tick after the instruction executed, so this reads the value before this cycle's tick
+    pub fn get_delay(&mut self, register: u8) {
+        self.registers[register as usize] = self.delay_timer;
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Fx0A): awaits a key being pressed and released and stores it into register x
+    // like the cosmac vip the key is taken on release, so holding a key doesn't fire repeated waits
+    // when several keys are released on the same cycle the lowest key wins so replays are deterministic
+    pub fn wait_key(&mut self, register: u8) {
+        // releases from before the wait started don't count
+        if !self.waiting_for_key {
+            self.waiting_for_key = true;
+            for ii in &mut self.kp_released { *ii = false; }
+            return;
+        }
+
+        if let Some(key) = self.kp_released.iter().position(|released| *released) {
+            self.registers[register as usize] = key as u8;
+            self.waiting_for_key = false;
+            self.set_pc(self.pc + 2);
+        }
+    }
+
+    // instruction(Fx15): sets delay timer to value of register x 
+    pub fn set_delay(&mut self, register: u8) {
+        self.delay_timer = self.registers[register as usize];
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Fx18): sets sound timer to value of register x 
+    pub fn set_sound(&mut self, register: u8) {
+        self.sound_timer = self.registers[register as usize];
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Fx1E): adds register x to index, set register F to 1 if operation wraps around, 0 if not
+    pub fn add_indx(&mut self, register: u8) {
+        let value = self.registers[register as usize] as u16;
+        let overflow = value as u32 + self.index as u32 > 0xFFF;
+
+        self.index = self.index.wrapping_add(value);
+
+        if self.quirks.index_overflow_sets_vf {
+            self.registers[0x0F] = overflow as u8;
+        }
+
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Fx29): gets font character for digit stored in register and sets index to it
+    pub fn get_font_chr(&mut self, register: u8) {
+        let val = self.registers[register as usize];
+        self.index = (FONT_START + (5 * val as usize)) as u16;
+        self.set_pc(self.pc + 2);
+    }
+
+    // memory address offset bytes past the index register, wraps at the end of memory instead of running past it
+    fn indexed(&self, offset: usize) -> usize {
+        (self.index as usize + offset) % self.memory.len()
+    }
+
+    // instruction(Fx33): saves most significant bits of register into memory at index
+    pub fn encode_save(&mut self, register: u8) {
+        let value = self.registers[register as usize];
+        let (hundreds, tens, ones) = (self.indexed(0), self.indexed(1), self.indexed(2));
+        self.write_memory(hundreds, value / 100);
+        self.write_memory(tens, (value / 10) % 10);
+        self.write_memory(ones, value % 10);
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Fx55): save register 0 until register x to memory starting at index, index moves past them with the quirk
+    pub fn save(&mut self, register: u8) {
+        for ii in 0..register + 1 {
+            let address = self.indexed(ii as usize);
+            self.write_memory(address, self.registers[ii as usize]);
+        }
+
+        if self.quirks.load_store_increments_i {
+            self.index = self.index.wrapping_add((register + 1) as u16);
+        }
+
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Fx65): load register 0 until register x from memory starting at index, index moves past them with the quirk
+    pub fn load(&mut self, register: u8) {
+        for ii in 0..register + 1 {
+            self.registers[ii as usize] = self.memory[self.indexed(ii as usize)];
+        }
+
+        if self.quirks.load_store_increments_i {
+            self.index = self.index.wrapping_add((register + 1) as u16);
+        }
+
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Fx75): save register 0 until register x to the rpl user flags, there are only 8 so x is capped at 7
+    pub fn save_flags(&mut self, register: u8) {
+        let count = (register as usize + 1).min(self.rpl_flags.len());
+        self.rpl_flags[..count].copy_from_slice(&self.registers[..count]);
+
+        self.set_pc(self.pc + 2);
+    }
+
+    // instruction(Fx85): load register 0 until register x from the rpl user flags, x is capped at 7
+    pub fn load_flags(&mut self, register: u8) {
+        let count = (register as usize + 1).min(self.rpl_flags.len());
+        self.registers[..count].copy_from_slice(&self.rpl_flags[..count]);
+
+        self.set_pc(self.pc + 2);
+    }
+}
+
+// indices at which the two slices differ, only the differences are allocated
+fn diff_indices<T: PartialEq>(a: &[T], b: &[T]) -> Vec<usize> {
+    a.iter().zip(b.iter()).enumerate().filter(|(_, (x, y))| x != y).map(|(ii, _)| ii).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compat::displays_glyph;
+
+    fn load(program: &[u8]) -> Chip8 {
+        let mut ch8 = Chip8::default();
+        ch8.load_rom_bytes(program).unwrap();
+
+        ch8
+    }
+
+    // counts the frames it is handed
+    struct FrameCounter {
+        frames: usize
+    }
+
+    impl Renderer for FrameCounter {
+        fn present(&mut self, _display: &[u32]) {
+            self.frames += 1;
+        }
+    }
+
+    // two Dxyn and a 00E0 followed by a jump to itself, presenting after every cycle should give exactly three frames
+    #[test]
+    fn presents_each_draw() {
+        let mut ch8 = load(&[0xF0, 0x29, 0xD0, 0x05, 0xD0, 0x05, 0x00, 0xE0, 0x12, 0x08]);
+        let mut counter = FrameCounter { frames: 0 };
+
+        for _ in 0..20 {
+            ch8.cycle().unwrap();
+            ch8.present(&mut counter);
+        }
+
+        assert_eq!(counter.frames, 3);
+    }
+
+    #[test]
+    fn cycles_per_frame_math() {
+        for (rate, expected) in &[(700, 12), (600, 10), (1000, 17), (60, 1), (30, 1), (1, 1)] {
+            let mut ch8 = Chip8::default();
+            ch8.set_instruction_rate(*rate);
+
+            assert_eq!(ch8.cycles_per_frame(), *expected, "at {} instructions per second", rate);
+        }
+    }
+
+    // a tight loop run frame by frame executes the same amount of instructions every frame and ticks the delay timer once per frame
+    #[test]
+    fn constant_frame_pacing() {
+        // V0 = 30, delay = V0, then spins on 1204
+        let mut ch8 = load(&[0x60, 0x1E, 0xF0, 0x15, 0x12, 0x04]);
+
+        for frame in 0..10 {
+            let before = ch8.instructions();
+
+            assert_eq!(ch8.run_frame(), Ok(ch8.cycles_per_frame() as usize));
+            assert_eq!(ch8.instructions() - before, ch8.cycles_per_frame() as u64);
+            assert_eq!(ch8.delay_timer(), 30 - (frame + 1));
+        }
+    }
+
+    // cycle_one describes each instruction it ran and nothing once the machine halted
+    #[test]
+    fn describes_executed_instructions() {
+        let mut ch8 = load(&[0x60, 0x05, 0xA3, 0x00, 0x22, 0x08, 0x00, 0x00, 0x00, 0xEE, 0x00, 0xEE]);
+        let expected = [
+            (0x200, 0x6005, Opcode::LdReg(0, 5), "LD V0, 0x05"),
+            (0x202, 0xA300, Opcode::LdIndx(0x300), "LD I, 0x300"),
+            (0x204, 0x2208, Opcode::Call(0x208), "CALL 0x208"),
+            (0x208, 0x00EE, Opcode::Ret, "RET")
+        ];
+
+        for (pc, opcode, instr, mnemonic) in &expected {
+            let executed = DecodedInstr { pc: *pc, opcode: *opcode, instr: *instr, mnemonic: String::from(*mnemonic) };
+            assert_eq!(ch8.cycle_one(), Ok(Some(executed)));
+        }
+
+        // 0x206 is a 0nnn machine code call, skipped unless strict, then 0x208 returns with an empty stack
+        assert!(ch8.cycle_one().is_ok());
+        assert!(ch8.cycle_one().is_err());
+        assert_eq!(ch8.cycle_one(), Ok(None));
+    }
+
+    // dump_registers and dump_stack after a call with known register values
+    #[test]
+    fn dumps_registers_and_stack() {
+        // VA = 0xAB, I = 0x123, delay = VA, call 0x20A which sets VF = 0x0F and spins
+        let mut ch8 = load(&[0x6A, 0xAB, 0xA1, 0x23, 0xFA, 0x15, 0x22, 0x0A, 0x00, 0x00, 0x6F, 0x0F, 0x12, 0x0C]);
+        ch8.run_cycles(5).unwrap();
+        let registers = ch8.dump_registers();
+
+        assert!(registers.contains("V8: 00  V9: 00  VA: AB  VB: 00"), "{}", registers);
+        assert!(registers.contains("VF: 0F"), "{}", registers);
+        assert!(registers.contains("I:  0123  PC: 020C  SP: 01"), "{}", registers);
+        assert!(registers.contains("DT: AB"), "{}", registers);
+        assert_eq!(ch8.dump_stack(), "0: 0206\n");
+    }
+
+    // key 5 goes down and up again, key 16 doesn't exist and is ignored
+    #[test]
+    fn sets_input_in_range() {
+        let mut ch8 = Chip8::default();
+
+        ch8.set_input(5, true);
+        assert!(ch8.get_input(5));
+        assert_eq!(ch8.snapshot().kp_input[5], 1);
+
+        ch8.set_input(5, false);
+        assert!(!ch8.get_input(5));
+        assert_eq!(ch8.snapshot().kp_input[5], 0);
+
+        ch8.set_input(16, true);
+        assert!(!ch8.get_input(16));
+        assert!(ch8.snapshot().kp_input.iter().all(|state| *state == 0));
+    }
+
+    // the read-only accessors after a call, a store and a register load
+    #[test]
+    fn inspects_state() {
+        // V0 = 0x42, V1 = 0x24, I = 0x300, [I] = V0 - V1, call 0x20C which spins
+        let mut ch8 = load(&[0x60, 0x42, 0x61, 0x24, 0xA3, 0x00, 0xF1, 0x55, 0x22, 0x0C, 0x00, 0x00, 0x12, 0x0C]);
+        ch8.run_cycles(5).unwrap();
+
+        assert_eq!(ch8.registers()[..2], [0x42, 0x24]);
+        assert_eq!(ch8.registers().len(), 16);
+        assert_eq!(ch8.memory()[0x300..0x302], [0x42, 0x24]);
+        assert_eq!(ch8.memory().len(), ch8.memory_size());
+        assert_eq!(ch8.stack()[0], 0x208);
+        assert_eq!(ch8.sp(), 1);
+        assert_eq!(ch8.pc_usize(), 0x20C);
+        assert_eq!(ch8.get_index(), 0x300);
+        assert_eq!(ch8.peek(0x301), Some(0x24));
+        assert_eq!(ch8.peek(0x1000), None);
+    }
+
+    // a loop of three instructions stops on the breakpoint every time around
+    #[test]
+    fn stops_at_breakpoint() {
+        // V0 += 1, V1 += 2, back to the start
+        let mut ch8 = load(&[0x70, 0x01, 0x71, 0x02, 0x12, 0x00]);
+        ch8.add_breakpoint(0x202);
+
+        assert_eq!(ch8.run_until_breakpoint(100), Ok(Some(0x202)));
+        assert_eq!(ch8.get_pc(), 0x202);
+        assert_eq!(ch8.get_register(0), 1);
+
+        assert_eq!(ch8.run_until_breakpoint(100), Ok(Some(0x202)));
+        assert_eq!(ch8.get_register(0), 2);
+        assert_eq!(ch8.get_register(1), 2);
+
+        ch8.remove_breakpoint(0x202);
+        assert_eq!(ch8.run_until_breakpoint(100), Ok(None));
+        assert_eq!(ch8.instructions(), 104);
+    }
+
+    // an Fx55 over a watched address reports the old and new value, unwatched writes don't
+    #[test]
+    fn fires_watchpoint() {
+        // V0 = 0x11, V1 = 0x22, I = 0x300, [I] = V0 - V1
+        let mut ch8 = load(&[0x60, 0x11, 0x61, 0x22, 0xA3, 0x00, 0xF1, 0x55]);
+        ch8.set_memory(0x301, 0x99);
+        ch8.add_watchpoint(0x301);
+        ch8.run_cycles(4).unwrap();
+
+        assert_eq!(ch8.take_watch_hits(), [WatchHit { pc: 0x206, address: 0x301, old: 0x99, new: 0x22 }]);
+        assert!(ch8.take_watch_hits().is_empty());
+    }
+
+    // display_rgba is 4 bytes for each of scale x scale pixels per display pixel, in both resolutions, and starts with the lit top left pixel
+    #[test]
+    fn exports_display_rgba() {
+        // draw the 0 glyph at 0,0, then switch to hi-res
+        let mut ch8 = load(&[0xA0, 0x50, 0xD0, 0x05, 0x00, 0xFF]);
+        ch8.run_cycles(2).unwrap();
+        let lores = ch8.display_rgba(3);
+        let row = VIDEO_WIDTH * 3 * 4;
+
+        assert_eq!(lores.len(), VIDEO_WIDTH * VIDEO_HEIGHT * 3 * 3 * 4);
+        assert_eq!(lores[..4], [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(lores[row * 3 - 4..row * 3], [0x00, 0x00, 0x00, 0xFF]);
+
+        ch8.run_cycles(1).unwrap();
+        assert_eq!(ch8.display_rgba(2).len(), HIRES_VIDEO_WIDTH * HIRES_VIDEO_HEIGHT * 2 * 2 * 4);
+    }
+
+    // the calls the browser page makes through wasm.rs, a frame of a rom that waits for key 5 and draws its digit
+    #[test]
+    fn runs_like_the_browser() {
+        // V0 = key, I = font digit V0, draw it at 0,0, then spin
+        let mut ch8 = load(&[0xF0, 0x0A, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x06]);
+
+        ch8.set_input(5, true);
+        ch8.run_frame().unwrap();
+        ch8.set_input(5, false);
+        ch8.run_frame().unwrap();
+
+        let (width, height) = ch8.display_dimensions();
+        assert!(!ch8.is_halted());
+        assert_eq!(ch8.display().len(), width * height);
+        assert!(displays_glyph(&ch8, &[0xF0, 0x80, 0xF0, 0x10, 0xF0]));
+    }
+
+    // a rom that touches registers, timers, the stack, memory, the display and the resolution, after a reset the
+    // machine has to match a freshly booted one with the same rom, self-modified memory included
+    #[test]
+    fn resets_to_boot_state() {
+        // V0 = 0x42, DT = ST = V0, [0x300] = V0, draw, 00FF, call a subroutine that spins
+        let rom = [0x60, 0x42, 0xF0, 0x15, 0xF0, 0x18, 0xA3, 0x00, 0xF0, 0x55, 0xD0, 0x15, 0x00, 0xFF, 0x22, 0x12, 0x12, 0x12];
+        let mut ch8 = load(&rom);
+        let fresh = load(&rom);
+
+        ch8.set_input(3, true);
+        ch8.run_cycles(10).unwrap();
+        assert_eq!(ch8.get_memory(0x300), 0x42);
+        assert!(ch8.is_hires());
+        assert_eq!(ch8.sp(), 1);
+
+        ch8.reset();
+        assert!(ch8.snapshot() == fresh.snapshot());
+        assert_eq!(ch8.memory()[0x200..0x200 + rom.len()], rom);
+    }
+
+    // a rom that overwrites its own first instruction and stores data behind itself, reload restores the rom bytes
+    // and clears the data while the registers keep their values
+    #[test]
+    fn reloads_rom_bytes() {
+        // V0 = 0x13, V1 = 0x37, I = 0x200, [I] = V0 - V1, I = 0x300, [I] = V0 - V1
+        let rom = [0x60, 0x13, 0x61, 0x37, 0xA2, 0x00, 0xF1, 0x55, 0xA3, 0x00, 0xF1, 0x55];
+        let mut ch8 = load(&rom);
+        ch8.run_cycles(6).unwrap();
+
+        assert_eq!(ch8.rom_bytes()[..2], [0x13, 0x37]);
+        assert_eq!(ch8.get_memory(0x301), 0x37);
+
+        ch8.reload();
+        assert_eq!(ch8.rom_bytes(), rom);
+        assert_eq!(ch8.loaded_rom(), rom);
+        assert_eq!(ch8.get_memory(0x300), 0);
+        assert_eq!(ch8.get_memory(0x301), 0);
+        assert_eq!(ch8.get_register(1), 0x37);
+        assert_eq!(ch8.get_pc(), 0x20C);
+    }
+
+    // two machines seeded alike draw the same Cxnn values, a different seed draws others
+    #[test]
+    fn seeded_random_repeats() {
+        let draws = |seed: u64| {
+            // V0 = rand & 0xFF, loop
+            let mut ch8 = load(&[0xC0, 0xFF, 0x12, 0x00]);
+            ch8.set_seed(seed);
+
+            (0..32).map(|_| {
+                ch8.run_cycles(2).unwrap();
+                ch8.get_register(0)
+            }).collect::<Vec<u8>>()
+        };
+
+        assert_eq!(draws(7), draws(7));
+        assert_ne!(draws(7), draws(8));
+    }
+
+    // 7xnn wraps around without touching VF
+    #[test]
+    fn add_val_wraps_without_carry() {
+        // V0 = 0xFF, VF = 0x0F, V0 += 1
+        let mut ch8 = load(&[0x60, 0xFF, 0x6F, 0x0F, 0x70, 0x01]);
+        ch8.run_cycles(3).unwrap();
+
+        assert_eq!(ch8.get_register(0), 0);
+        assert_eq!(ch8.get_register(0xF), 0x0F);
+    }
+
+    // the same B210 lands on 0x210 + V0 or 0x210 + V2 depending on the jump quirk at the time it runs
+    #[test]
+    fn jump_quirk_is_read_live() {
+        // V0 = 0x04, V2 = 0x08, B210
+        let rom = [0x60, 0x04, 0x62, 0x08, 0xB2, 0x10];
+        let mut ch8 = load(&rom);
+        ch8.run_cycles(3).unwrap();
+        let plain = ch8.get_pc();
+
+        ch8.reload();
+        ch8.set_pc(0x200);
+        ch8.set_quirks(Quirks { jump_uses_vx: true, ..ch8.quirks() });
+        ch8.run_cycles(3).unwrap();
+
+        assert_eq!(plain, 0x214);
+        assert_eq!(ch8.get_pc(), 0x218);
+    }
+
+    // a rom loaded from a slice reads back byte for byte, no longer and no shorter
+    #[test]
+    fn loads_rom_slice() {
+        let rom = [0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0C, 0x61, 0x08, 0xD0, 0x1F];
+        let ch8 = load(&rom);
+
+        assert_eq!(ch8.rom_bytes(), rom);
+        assert_eq!(ch8.memory()[ROM_START..ROM_START + rom.len()], rom);
+    }
+
+    // the fontset is there after boot and all zeroes once unloaded
+    #[test]
+    fn unloads_font() {
+        let mut ch8 = Chip8::default();
+        assert_eq!(ch8.memory()[FONT_START..FONT_START + CH8_FONT.len()], *CH8_FONT);
+
+        ch8.unload_font();
+        assert!(ch8.memory()[FONT_START..FONT_START + CH8_FONT.len()].iter().all(|byte| *byte == 0));
+    }
+
+    // 0123 is stepped over by default and halts the machine in strict mode
+    #[test]
+    fn skips_or_halts_on_sys() {
+        let mut ch8 = load(&[0x01, 0x23]);
+        ch8.cycle().unwrap();
+        assert_eq!(ch8.get_pc(), 0x202);
+        assert!(!ch8.is_halted());
+
+        let mut strict = load(&[0x01, 0x23]);
+        strict.set_strict(true);
+        assert_eq!(strict.cycle(), Err(Ch8Error::InvalidOpcode(0x123)));
+        assert_eq!(strict.get_pc(), 0x200);
+        assert!(strict.is_halted());
+    }
+
+    // unknown 8xyF and FxFF sub-opcodes move on to the next instruction instead of spinning in place
+    #[test]
+    fn advances_past_unknown_sub_opcodes() {
+        let mut ch8 = load(&[0x81, 0x2F, 0xF1, 0xFF]);
+
+        ch8.cycle().unwrap();
+        assert_eq!(ch8.get_pc(), 0x202);
+        ch8.cycle().unwrap();
+        assert_eq!(ch8.get_pc(), 0x204);
+
+        let mut strict = load(&[0x81, 0x2F]);
+        strict.set_strict(true);
+        assert_eq!(strict.cycle(), Err(Ch8Error::InvalidOpcode(0x812F)));
+    }
+
+    // the delay busy-wait is recognized from any of its three instructions and only while the timer runs
+    #[test]
+    fn detects_delay_busy_wait() {
+        // V0 = 3, DT = V0, then V1 = DT, skip if V1 == 0, jump back to the V1 = DT
+        let mut ch8 = load(&[0x60, 0x03, 0xF0, 0x15, 0xF1, 0x07, 0x31, 0x00, 0x12, 0x04]);
+        ch8.run_cycles(2).unwrap();
+
+        for _ in 0..3 {
+            assert!(ch8.is_waiting_on_delay(), "at {:#05X}", ch8.get_pc());
+            ch8.cycle().unwrap();
+        }
+
+        ch8.run_frame().unwrap();
+        ch8.run_frame().unwrap();
+        ch8.run_frame().unwrap();
+        assert!(!ch8.is_waiting_on_delay());
+    }
+
+    // a pc at the very start of memory looks back across the end of memory instead of overflowing
+    #[test]
+    fn busy_wait_check_wraps_at_zero() {
+        let mut ch8 = Chip8::new();
+        ch8.delay_timer = 10;
+        ch8.set_pc(0);
+
+        assert!(!ch8.is_waiting_on_delay());
+    }
+
+    // a state saved to a file and loaded into a machine that ran on restores everything it had at the save
+    #[test]
+    fn round_trips_state_file() {
+        let path = std::env::temp_dir().join(std::format!("ch8-rs-state-{}.sav", std::process::id()));
+
+        // V0 += 1, I = font glyph of V0's low nibble, draw it and loop
+        let mut ch8 = load(&[0x70, 0x01, 0x61, 0x0F, 0x81, 0x02, 0xF1, 0x29, 0xD2, 0x25, 0x12, 0x00]);
+        ch8.run_cycles(20).unwrap();
+        let saved = ch8.snapshot();
+        ch8.save_state_to_file(&path).unwrap();
+
+        ch8.run_cycles(20).unwrap();
+        ch8.load_state_from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(ch8.snapshot() == saved);
+    }
+
+    // a machine booted without the fontset comes back from a reset without it
+    #[test]
+    fn reset_keeps_font_unloaded() {
+        let mut ch8 = load(&[0x12, 0x00]);
+        ch8.unload_font();
+        ch8.reset();
+
+        assert!(ch8.memory()[FONT_START..FONT_START + CH8_FONT.len()].iter().all(|byte| *byte == 0));
+    }
+
+    // frozen timers hold their values across frames and count down again once thawed
+    #[test]
+    fn frozen_timers_hold() {
+        // V0 = 30, DT = ST = V0, then spin
+        let mut ch8 = load(&[0x60, 0x1E, 0xF0, 0x15, 0xF0, 0x18, 0x12, 0x06]);
+        ch8.run_cycles(3).unwrap();
+        ch8.freeze_timers(true);
+
+        for _ in 0..10 {
+            ch8.run_frame().unwrap();
+        }
+        assert_eq!((ch8.delay_timer(), ch8.sound_timer()), (30, 30));
+
+        ch8.freeze_timers(false);
+        ch8.run_frame().unwrap();
+        assert_eq!((ch8.delay_timer(), ch8.sound_timer()), (29, 29));
+    }
+
+    // diff lists exactly the register, memory, stack and pixel indices one machine changed
+    #[test]
+    fn diffs_states() {
+        // V3 = 0x42, I = 0x300, [I] = V0 - V3, draw the 0x300 sprite at 0,0 (V0 is 0), call 0x20C which spins
+        let rom = [0x63, 0x42, 0xA3, 0x00, 0xF3, 0x55, 0xD0, 0x04, 0x22, 0x0C, 0x00, 0x00, 0x12, 0x0C];
+        let fresh = load(&rom);
+        let mut ch8 = load(&rom);
+
+        assert!(ch8.diff(&fresh).is_empty());
+
+        ch8.run_cycles(5).unwrap();
+        let diff = ch8.diff(&fresh);
+
+        assert_eq!(diff.registers, [3]); // nothing collided, VF stays 0
+        assert_eq!(diff.memory, [0x303]);
+        assert_eq!(diff.stack, [0]);
+        // 0x42 is 01000010, drawn on the last of the four rows
+        assert_eq!(diff.display, [VIDEO_WIDTH * 3 + 1, VIDEO_WIDTH * 3 + 6]);
+    }
+
+    // the instruction in the last word of memory steps the pc over the 12 bit boundary back to 0
+    #[test]
+    fn pc_wraps_at_12_bits() {
+        // jump to 0xFFE, which holds 00E0
+        let mut ch8 = load(&[0x1F, 0xFE]);
+        ch8.set_memory(0xFFE, 0x00);
+        ch8.set_memory(0xFFF, 0xE0);
+
+        ch8.cycle().unwrap();
+        assert_eq!(ch8.get_pc(), 0xFFE);
+
+        ch8.cycle().unwrap();
+        assert_eq!(ch8.get_pc(), 0x000);
+        assert!(!ch8.is_halted());
+    }
+
+    // a key pressed and released between two cycles is still seen by the next Ex9E, and only once
+    #[test]
+    fn sees_tap_between_cycles() {
+        // V0 = 5, skip the V1 = 1 if key V0 is down, then the same again for V2
+        let mut ch8 = load(&[0x60, 0x05, 0xE0, 0x9E, 0x61, 0x01, 0xE0, 0x9E, 0x62, 0x01]);
+        ch8.cycle().unwrap();
+
+        ch8.set_input(5, true);
+        ch8.set_input(5, false);
+        ch8.run_cycles(3).unwrap();
+
+        assert_eq!(ch8.get_register(1), 0);
+        assert_eq!(ch8.get_register(2), 1);
+    }
+
+    // with 8k of memory a rom can be loaded past 4k and the index register reaches above 0xFFF
+    #[test]
+    fn uses_memory_above_4k() {
+        let mut ch8 = Chip8::default();
+        ch8.set_memory_size(8192).unwrap();
+
+        let rom = vec![0xAB; 5000];
+        ch8.load_rom_bytes(&rom).unwrap();
+        assert_eq!(ch8.memory_size(), 8192);
+        assert_eq!(ch8.rom_bytes(), rom.as_slice());
+
+        // I = 0xFFF, V0 = 0x20, I += V0, [I] = V0
+        ch8.load_rom_bytes(&[0xAF, 0xFF, 0x60, 0x20, 0xF0, 0x1E, 0xF0, 0x55]).unwrap();
+        ch8.run_cycles(4).unwrap();
+        assert_eq!(ch8.get_index(), 0x101F);
+        assert_eq!(ch8.get_memory(0x101F), 0x20);
+    }
+
+    // a rom past 4k keeps memory from shrinking back, the rom and the memory map stay intact
+    #[test]
+    fn refuses_to_shrink_below_rom() {
+        let mut ch8 = Chip8::default();
+        ch8.set_memory_size(8192).unwrap();
+
+        let rom = vec![0xAB; 5000];
+        ch8.load_rom_bytes(&rom).unwrap();
+
+        assert_eq!(ch8.set_memory_size(4096), Err(Ch8Error::RomTooLarge(5000)));
+        assert_eq!(ch8.memory_size(), 8192);
+        assert_eq!(ch8.rom_bytes(), rom.as_slice());
+        assert!(ch8.memory_map().contains("free memory: 2680 bytes"));
+
+        ch8.set_memory_size(ROM_START + 5000).unwrap();
+        assert_eq!(ch8.memory_size(), ROM_START + 5000);
+        assert!(ch8.memory_map().contains("free memory: 0 bytes"));
+    }
+
+    // in hi-res VF counts the rows that collided or wrapped off the bottom, each row once
+    #[test]
+    fn counts_collided_and_clipped_rows_in_hires() {
+        // 00FF, I = glyph 0, draw it at 0,62 and again at 0,60
+        let mut ch8 = load(&[0x00, 0xFF, 0x60, 0x00, 0x61, 0x3E, 0xF0, 0x29, 0xD0, 0x15, 0x61, 0x3C, 0xD0, 0x15]);
+        ch8.set_quirks(Quirks { schip_collision_count: true, ..Quirks::default() });
+
+        // no collisions, the last 3 rows wrap off the bottom
+        ch8.run_cycles(5).unwrap();
+        assert_eq!(ch8.get_register(0xF), 3);
+
+        // the middle 2 rows land on the first glyph's top 2 rows, the last row wraps off the bottom
+        ch8.run_cycles(2).unwrap();
+        assert_eq!(ch8.get_register(0xF), 3);
+        assert_eq!(ch8.get_display(60 * HIRES_VIDEO_WIDTH), 1);
+    }
+
+    // the quirk leaves lo-res alone, VF only says whether anything collided
+    #[test]
+    fn keeps_collision_flag_in_lores() {
+        // I = glyph 0, draw it at 0,30 twice
+        let mut ch8 = load(&[0x60, 0x00, 0x61, 0x1E, 0xF0, 0x29, 0xD0, 0x15, 0xD0, 0x15]);
+        ch8.set_quirks(Quirks { schip_collision_count: true, ..Quirks::default() });
+
+        ch8.run_cycles(4).unwrap();
+        assert_eq!(ch8.get_register(0xF), 0);
+
+        ch8.run_cycles(1).unwrap();
+        assert_eq!(ch8.get_register(0xF), 1);
+    }
+
+    // a press queued for instruction 5 is down once 5 instructions ran, so the 6th sees it and the 5th doesn't
+    #[test]
+    fn applies_queued_keypress() {
+        // four V0 = 0, then twice: V1 (V2) += 1 unless key 0 is up
+        let mut ch8 = load(&[0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xE0, 0xA1, 0x71, 0x01, 0xE0, 0xA1, 0x72, 0x01]);
+        ch8.queue_keypress(0, 5, 7).unwrap();
+
+        ch8.run_cycles(4).unwrap();
+        assert!(!ch8.get_input(0));
+        ch8.run_cycles(1).unwrap();
+        assert!(ch8.get_input(0));
+        ch8.run_cycles(2).unwrap();
+        assert!(!ch8.get_input(0));
+
+        assert_eq!(ch8.get_register(1), 0);
+        assert_eq!(ch8.get_register(2), 1);
+
+        assert_eq!(ch8.queue_keypress(0x10, 1, 2), Err(Ch8Error::InvalidKey(0x10)));
+        assert_eq!(ch8.queue_keypress(1, 20, 20), Err(Ch8Error::ReleaseBeforePress(1, 20, 20)));
+    }
+
+    // a rom without an endless loop runs off its end into zeroed memory, which halts with --halt-on-rom-end
+    #[test]
+    fn halts_past_rom_end() {
+        let mut ch8 = load(&[0x60, 0x01, 0x61, 0x02]);
+        ch8.set_halt_on_rom_end(true);
+
+        ch8.cycle().unwrap();
+        assert_eq!(ch8.cycle(), Err(Ch8Error::PastRomEnd(0x204)));
+        assert!(ch8.is_halted());
+        assert_eq!(ch8.get_register(1), 2);
+
+        let mut running = load(&[0x60, 0x01, 0x61, 0x02]);
+        running.run_cycles(3).unwrap();
+        assert!(!running.is_halted());
+    }
+
+    // a default machine boots like Chip8::new, at 0x200 with the font loaded and nothing running
+    #[test]
+    fn default_boots_at_rom_start() {
+        let ch8 = Chip8::default();
+
+        assert_eq!(ch8.get_pc(), 0x200);
+        assert_eq!(ch8.sp(), 0);
+        assert_eq!(ch8.instructions(), 0);
+        assert!(!ch8.is_halted());
+        assert_eq!(ch8.memory()[FONT_START..FONT_START + CH8_FONT.len()], *CH8_FONT);
+    }
+
+    // the timer accessors show what Fx15 and Fx18 stored
+    #[test]
+    fn reads_timers() {
+        // V0 = 0x20, V1 = 0x0A, DT = V0, ST = V1
+        let mut ch8 = load(&[0x60, 0x20, 0x61, 0x0A, 0xF0, 0x15, 0xF1, 0x18]);
+        ch8.run_cycles(3).unwrap();
+        assert_eq!((ch8.delay_timer(), ch8.sound_timer()), (0x20, 0));
+
+        ch8.run_cycles(1).unwrap();
+        assert_eq!((ch8.delay_timer(), ch8.sound_timer()), (0x20, 0x0A));
+    }
+
+    // keys 3 and 7 held together and let go on the same cycle complete the Fx0A wait with the lower one
+    #[test]
+    fn wait_key_takes_lowest_key() {
+        // V0 = key, V1 = 1
+        let mut ch8 = load(&[0xF0, 0x0A, 0x61, 0x01]);
+        ch8.registers[0] = 0xFF;
+        ch8.cycle().unwrap();
+
+        ch8.set_input(7, true);
+        ch8.set_input(3, true);
+        ch8.run_cycles(5).unwrap();
+        assert_eq!(ch8.get_pc(), 0x200);
+
+        ch8.set_input(7, false);
+        ch8.set_input(3, false);
+        ch8.run_cycles(2).unwrap();
+        assert_eq!(ch8.get_register(0), 3);
+        assert_eq!(ch8.get_register(1), 1);
+    }
+
+    // Fx0A keeps waiting while the key is only pressed and completes on its release, a tap from before the wait doesn't count
+    #[test]
+    fn wait_key_completes_on_release() {
+        // V0 = key, V1 = 1
+        let mut ch8 = load(&[0xF0, 0x0A, 0x61, 0x01]);
+        ch8.set_input(2, true);
+        ch8.set_input(2, false);
+        ch8.cycle().unwrap();
+
+        ch8.set_input(9, true);
+        ch8.run_cycles(5).unwrap();
+        assert_eq!(ch8.get_pc(), 0x200);
+        assert_eq!(ch8.get_register(0), 0);
+
+        ch8.set_input(9, false);
+        ch8.run_cycles(2).unwrap();
+        assert_eq!(ch8.get_register(0), 9);
+        assert_eq!(ch8.get_register(1), 1);
+    }
+
+    // every builder setting reaches the machine, the start pc is masked to 12 bits like any other pc
+    #[test]
+    fn builds_configured_machine() {
+        let quirks = Quirks { shift_uses_vy: true, ..Quirks::default() };
+        let ch8 = Chip8Builder::new().quirks(quirks).clock_hz(1200).start_pc(0x1300).build();
+
+        assert_eq!(ch8.get_pc(), 0x300);
+        assert_eq!(ch8.cycles_per_frame(), 20);
+        assert!(ch8.quirks().shift_uses_vy);
+        assert!(!ch8.quirks().jump_uses_vx);
+
+        // the same seed draws the same Cxnn values
+        let draw = |seed: u64| {
+            let mut ch8 = Chip8Builder::new().seed(seed).build();
+            ch8.load_rom_bytes(&[0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF]).unwrap();
+            ch8.run_cycles(3).unwrap();
+            ch8.registers()[..3].to_vec()
+        };
+        assert_eq!(draw(42), draw(42));
+    }
+
+    // the F5/F9 state slot: restoring a snapshot brings back registers, stack, memory, display and timers,
+    // and frames run from there play out exactly as they did the first time
+    #[test]
+    fn restores_snapshot_slot() {
+        // V0 += 1, DT = V0, I = font glyph of V0, draw it, call a subroutine that returns, loop
+        let mut ch8 = load(&[0x70, 0x01, 0xF0, 0x15, 0xF0, 0x29, 0xD1, 0x15, 0x22, 0x0E, 0x12, 0x00, 0x00, 0x00, 0x00, 0xEE]);
+        ch8.run_frame().unwrap();
+        let slot = ch8.snapshot();
+
+        for _ in 0..5 {
+            ch8.run_frame().unwrap();
+        }
+        let after = ch8.snapshot();
+        assert!(after != slot);
+
+        ch8.restore(&slot);
+        assert!(ch8.snapshot() == slot);
+
+        for _ in 0..5 {
+            ch8.run_frame().unwrap();
+        }
+        assert!(ch8.snapshot() == after);
+    }
+
+    // 3584 bytes fill memory from 0x200 to the end, one more byte is rejected without touching memory
+    #[test]
+    fn rejects_oversized_rom() {
+        let mut ch8 = Chip8::default();
+        assert!(ch8.load_rom_bytes(&vec![0x11; MEMORY_SIZE - ROM_START]).is_ok());
+
+        let mut fresh = Chip8::default();
+        assert_eq!(fresh.load_rom_bytes(&vec![0x11; MEMORY_SIZE - ROM_START + 1]), Err(Ch8Error::RomTooLarge(3585)));
+        assert!(fresh.rom_bytes().is_empty());
+        assert_eq!(fresh.get_memory(ROM_START), 0);
+    }
+
+    // the hash only depends on which pixels are lit, it changes with the picture and is the same for the same picture
+    #[test]
+    fn hashes_display() {
+        // I = glyph 0, draw it at 0,0, then loop
+        let rom = [0xA0, 0x50, 0xD0, 0x05, 0x12, 0x04];
+        let mut ch8 = load(&rom);
+        let mut again = load(&rom);
+        let blank = ch8.display_hash();
+
+        assert_eq!(ch8.run_until_draw(), Ok(2));
+        again.run_cycles(2).unwrap();
+        assert_ne!(ch8.display_hash(), blank);
+        assert_eq!(ch8.display_hash(), again.display_hash());
+
+        // the same pixels lit through plane 2 instead of plane 1
+        let planes: Vec<u32> = ch8.display().iter().map(|pixel| pixel << 1).collect();
+        ch8.display[..planes.len()].copy_from_slice(&planes);
+        assert_eq!(ch8.display_hash(), again.display_hash());
+
+        // pinned so the hashes stored for test roms stay valid
+        assert_eq!(blank, 0x28c3_1cf8_df2e_c325);
+    }
+
+    // a one second beep loses one tick per 60hz frame, and the same when the frames are made of single cycles
+    #[test]
+    fn counts_down_beep() {
+        // V0 = 60, ST = V0, then spin
+        let mut ch8 = load(&[0x60, 60, 0xF0, 0x18, 0x12, 0x04]);
+        ch8.run_cycles(2).unwrap();
+        assert_eq!(ch8.sound_timer(), 60);
+
+        for _ in 0..15 {
+            ch8.run_frame().unwrap();
+        }
+        assert_eq!(ch8.sound_timer(), 45);
+
+        ch8.run_cycles((INSTRUCTION_RATE / 2) as usize).unwrap();
+        assert!((14..=16).contains(&ch8.sound_timer()));
+
+        ch8.run_cycles(INSTRUCTION_RATE as usize).unwrap();
+        assert_eq!(ch8.sound_timer(), 0);
+        assert!(!ch8.is_beeping());
+    }
 }
@@ -0,0 +1,463 @@
+use crate::ch8::{Chip8, INSTRUCTION_RATE, Quirks, TIMER_RATE, VIDEO_HEIGHT, VIDEO_WIDTH};
+
+// a tiny program loaded at the rom start, run for a fixed amount of cycles and checked against the expected state
+struct MicroTest {
+    name: &'static str,
+    quirks: Quirks,
+    program: &'static [u8],
+    cycles: usize,
+    check: fn(&Chip8) -> bool
+}
+
+const DEFAULT_QUIRKS: Quirks = Quirks {
+    jump_uses_vx: false,
+    shift_vf_keeps_result: false,
+    schip_collision_count: false,
+    shift_uses_vy: false,
+    load_store_increments_i: false,
+    index_overflow_sets_vf: false
+};
+
+static MICRO_TESTS: &[MicroTest] = &[
+    MicroTest {
+        name: "3xnn skip if equal",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x05, 0x30, 0x05, 0x6A, 0x01, 0x6B, 0x01],
+        cycles: 3,
+        check: |c| c.get_register(0xA) == 0 && c.get_register(0xB) == 1
+    },
+    MicroTest {
+        name: "2nnn/00EE call and return",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x22, 0x06, 0x6A, 0x01, 0x12, 0x04, 0x00, 0xEE],
+        cycles: 3,
+        check: |c| c.get_register(0xA) == 1
+    },
+    MicroTest {
+        name: "2nnn 17 nested calls overflow",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x22, 0x00],
+        cycles: 17,
+        check: |c| c.is_halted() && c.snapshot().sp == 16
+    },
+    MicroTest {
+        name: "00EE without a call underflows",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x00, 0xEE],
+        cycles: 1,
+        check: |c| c.is_halted() && c.snapshot().sp == 0
+    },
+    MicroTest {
+        name: "7xnn wraps without VF",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x6F, 0x05, 0x60, 0xFF, 0x70, 0x01],
+        cycles: 3,
+        check: |c| c.get_register(0) == 0 && c.get_register(0xF) == 5
+    },
+    MicroTest {
+        name: "8xy4 carry sets VF",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0xFF, 0x61, 0x01, 0x80, 0x14],
+        cycles: 3,
+        check: |c| c.get_register(0) == 0 && c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "8xy5 no borrow sets VF",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x05, 0x61, 0x03, 0x80, 0x15],
+        cycles: 3,
+        check: |c| c.get_register(0) == 2 && c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "8xy5 borrow clears VF",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x03, 0x61, 0x05, 0x80, 0x15],
+        cycles: 3,
+        check: |c| c.get_register(0) == 0xFE && c.get_register(0xF) == 0
+    },
+    MicroTest {
+        name: "8xy7 no borrow sets VF",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x03, 0x61, 0x05, 0x80, 0x17],
+        cycles: 3,
+        check: |c| c.get_register(0) == 2 && c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "8xy7 borrow clears VF",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x05, 0x61, 0x03, 0x80, 0x17],
+        cycles: 3,
+        check: |c| c.get_register(0) == 0xFE && c.get_register(0xF) == 0
+    },
+    MicroTest {
+        name: "8xy5/8xy7 equal operands set VF",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x04, 0x61, 0x04, 0x80, 0x15, 0x82, 0xF0, 0x61, 0x00, 0x81, 0x17],
+        cycles: 6,
+        check: |c| c.get_register(0) == 0 && c.get_register(2) == 1 && c.get_register(1) == 0 && c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "8xy4 into VF keeps the flag",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x6F, 0xFF, 0x61, 0x01, 0x8F, 0x14],
+        cycles: 3,
+        check: |c| c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "8xy5 into VF keeps the flag",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x6F, 0x05, 0x61, 0x03, 0x8F, 0x15],
+        cycles: 3,
+        check: |c| c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "8xy7 into VF keeps the flag",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x6F, 0x05, 0x61, 0x03, 0x8F, 0x17],
+        cycles: 3,
+        check: |c| c.get_register(0xF) == 0
+    },
+    MicroTest {
+        name: "8xy6 shift right",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x05, 0x80, 0x06],
+        cycles: 2,
+        check: |c| c.get_register(0) == 2 && c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "8xy6 shifts Vx in place",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x05, 0x61, 0x08, 0x80, 0x16],
+        cycles: 3,
+        check: |c| c.get_register(0) == 2 && c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "8xy6 shifts Vy into Vx (quirk)",
+        quirks: Quirks { shift_uses_vy: true, ..DEFAULT_QUIRKS },
+        program: &[0x60, 0x05, 0x61, 0x08, 0x80, 0x16],
+        cycles: 3,
+        check: |c| c.get_register(0) == 4 && c.get_register(1) == 8 && c.get_register(0xF) == 0
+    },
+    MicroTest {
+        name: "8xyE shifts Vy into Vx (quirk)",
+        quirks: Quirks { shift_uses_vy: true, ..DEFAULT_QUIRKS },
+        program: &[0x60, 0x01, 0x61, 0x81, 0x80, 0x1E],
+        cycles: 3,
+        check: |c| c.get_register(0) == 2 && c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "8xyE shift left",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x81, 0x80, 0x0E],
+        cycles: 2,
+        check: |c| c.get_register(0) == 2 && c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "8xy6 on VF keeps the flag",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x6F, 0x05, 0x8F, 0x06],
+        cycles: 2,
+        check: |c| c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "8xy6 on VF keeps the result (quirk)",
+        quirks: Quirks { shift_vf_keeps_result: true, ..DEFAULT_QUIRKS },
+        program: &[0x6F, 0x05, 0x8F, 0x06],
+        cycles: 2,
+        check: |c| c.get_register(0xF) == 2
+    },
+    MicroTest {
+        name: "8xyE on VF keeps the flag",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x6F, 0x81, 0x8F, 0x0E],
+        cycles: 2,
+        check: |c| c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "Bnnn jumps to nnn + V0",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x02, 0xB2, 0x04, 0x6A, 0x01, 0x6B, 0x01],
+        cycles: 3,
+        check: |c| c.get_register(0xA) == 0 && c.get_register(0xB) == 1
+    },
+    MicroTest {
+        name: "Bxnn jumps to xnn + Vx (quirk)",
+        quirks: Quirks { jump_uses_vx: true, ..DEFAULT_QUIRKS },
+        program: &[0x62, 0x02, 0xB2, 0x04, 0x6A, 0x01, 0x6B, 0x01],
+        cycles: 3,
+        check: |c| c.get_register(0xA) == 0 && c.get_register(0xB) == 1
+    },
+    MicroTest {
+        name: "Dxyn collision sets VF",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x00, 0x61, 0x00, 0xA0, 0x50, 0xD0, 0x15, 0xD0, 0x15],
+        cycles: 5,
+        check: |c| c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "Dxyn partial overlap sets VF",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x00, 0x61, 0x00, 0xA0, 0x50, 0xD0, 0x15, 0x60, 0x02, 0xD0, 0x15],
+        cycles: 6,
+        check: |c| c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "Dxyn without collision clears VF",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x00, 0x61, 0x00, 0xA0, 0x50, 0xD0, 0x15, 0x60, 0x08, 0xD0, 0x15],
+        cycles: 6,
+        check: |c| c.get_register(0xF) == 0
+    },
+    MicroTest {
+        name: "Dxyn draws a font glyph via Fx29",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x00, 0x61, 0x00, 0x62, 0x07, 0xF2, 0x29, 0xD0, 0x15],
+        cycles: 5,
+        check: |c| displays_glyph(c, &[0xF0, 0x10, 0x20, 0x40, 0x40])
+    },
+    MicroTest {
+        name: "Dxyn wraps at the right and bottom",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x3E, 0x61, 0x1E, 0xA0, 0x50, 0xD0, 0x15],
+        cycles: 4,
+        // glyph 0 at 62,30, its left half stays on the bottom right and the rest wraps to the opposite edges
+        check: |c| c.get_display(30 * VIDEO_WIDTH + 62) == 1 && c.get_display(31 * VIDEO_WIDTH + 63) == 0
+            && c.get_display(30 * VIDEO_WIDTH + 1) == 1 && c.get_display(VIDEO_WIDTH) == 0 && c.get_display(2 * VIDEO_WIDTH + 1) == 1
+            && c.get_display(31 * VIDEO_WIDTH) == 0
+    },
+    MicroTest {
+        name: "Dxyn counts wrapped rows in VF in hi-res",
+        quirks: Quirks { schip_collision_count: true, ..DEFAULT_QUIRKS },
+        program: &[0x00, 0xFF, 0x60, 0x00, 0x61, 0x3E, 0xF0, 0x29, 0xD0, 0x15],
+        cycles: 5,
+        check: |c| c.get_register(0xF) == 3
+    },
+    MicroTest {
+        name: "Fx33 binary coded decimal",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0xFE, 0xA3, 0x00, 0xF0, 0x33],
+        cycles: 3,
+        check: |c| c.get_memory(0x300) == 2 && c.get_memory(0x301) == 5 && c.get_memory(0x302) == 4
+    },
+    MicroTest {
+        name: "Fx33 single digits",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x00, 0x61, 0x09, 0xA3, 0x00, 0xF0, 0x33, 0xA3, 0x03, 0xF1, 0x33],
+        cycles: 6,
+        check: |c| (0x300..0x306).map(|address| c.get_memory(address)).eq([0, 0, 0, 0, 0, 9].iter().copied())
+    },
+    MicroTest {
+        name: "Fx15/Fx18 set the timers",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x2A, 0xF0, 0x15, 0xF0, 0x18],
+        cycles: 3,
+        // the timers may already have ticked once since they were set
+        check: |c| c.delay_timer() >= 0x28 && c.delay_timer() <= 0x2A && c.sound_timer() >= 0x29 && c.sound_timer() <= 0x2A
+    },
+    MicroTest {
+        name: "Fx15 delay counts down at 60hz",
+        quirks: DEFAULT_QUIRKS,
+        // one second of instructions spinning on a jump after setting the delay
+        program: &[0x60, 0xFF, 0xF0, 0x15, 0x12, 0x04],
+        cycles: INSTRUCTION_RATE as usize + 2,
+        check: |c| c.delay_timer() == 0xFF - TIMER_RATE as u8
+    },
+    MicroTest {
+        name: "Fx33 wraps at the end of memory",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0xFE, 0xAF, 0xFF, 0xF0, 0x33],
+        cycles: 3,
+        check: |c| c.get_memory(0xFFF) == 2 && c.get_memory(0x000) == 5 && c.get_memory(0x001) == 4
+    },
+    MicroTest {
+        name: "pc at the end of memory halts",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x1F, 0xFF],
+        cycles: 3,
+        check: |c| c.is_halted() && c.get_pc() == 0xFFF
+    },
+    MicroTest {
+        name: "Fx55/Fx65 save and load",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x01, 0x61, 0x02, 0xA3, 0x00, 0xF1, 0x55, 0x60, 0x00, 0x61, 0x00, 0xA3, 0x00, 0xF1, 0x65],
+        cycles: 8,
+        check: |c| c.get_register(0) == 1 && c.get_register(1) == 2
+    },
+    MicroTest {
+        name: "Fx55/Fx65 leave I unchanged",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0xA3, 0x00, 0xF1, 0x55, 0xF1, 0x65],
+        cycles: 3,
+        check: |c| c.get_index() == 0x300
+    },
+    MicroTest {
+        name: "Fx55/Fx65 increment I (quirk)",
+        quirks: Quirks { load_store_increments_i: true, ..DEFAULT_QUIRKS },
+        program: &[0xA3, 0x00, 0xF1, 0x55, 0xF1, 0x65],
+        cycles: 3,
+        check: |c| c.get_index() == 0x304
+    },
+    MicroTest {
+        name: "Fx1E overflow leaves VF",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x6F, 0x55, 0x60, 0x01, 0xAF, 0xFF, 0xF0, 0x1E],
+        cycles: 4,
+        check: |c| c.get_index() == 0x1000 && c.get_register(0xF) == 0x55
+    },
+    MicroTest {
+        name: "Fx1E overflow sets VF (quirk)",
+        quirks: Quirks { index_overflow_sets_vf: true, ..DEFAULT_QUIRKS },
+        program: &[0x6F, 0x55, 0x60, 0x01, 0xAF, 0xFF, 0xF0, 0x1E],
+        cycles: 4,
+        check: |c| c.get_index() == 0x1000 && c.get_register(0xF) == 1
+    },
+    MicroTest {
+        name: "Fx1E in range clears VF (quirk)",
+        quirks: Quirks { index_overflow_sets_vf: true, ..DEFAULT_QUIRKS },
+        program: &[0x6F, 0x55, 0x60, 0x01, 0xAF, 0xFE, 0xF0, 0x1E],
+        cycles: 4,
+        check: |c| c.get_index() == 0xFFF && c.get_register(0xF) == 0
+    },
+    MicroTest {
+        name: "Fx55 self-modifying code runs",
+        quirks: DEFAULT_QUIRKS,
+        // writes 6A07 over the 0000 at 0x20C and jumps there
+        program: &[0x60, 0x6A, 0x61, 0x07, 0xA2, 0x0C, 0xF1, 0x55, 0x12, 0x0C, 0x00, 0x00, 0x00, 0x00],
+        cycles: 6,
+        check: |c| c.get_register(0xA) == 7 && c.get_memory(0x20C) == 0x6A
+    },
+    MicroTest {
+        name: "00Cn scroll down clears the top",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x00, 0xC2],
+        cycles: 4,
+        check: |c| displays_glyph(c, &[0x00, 0x00, 0xF0, 0x90, 0x90, 0x90, 0xF0])
+    },
+    MicroTest {
+        name: "00FB/00FC scroll right and left",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x00, 0xFB, 0x00, 0xFB, 0x00, 0xFC],
+        cycles: 6,
+        check: |c| displays_glyph(c, &[0x0F, 0x09, 0x09, 0x09, 0x0F])
+    },
+    MicroTest {
+        name: "headless run draws glyphs 0-3",
+        quirks: DEFAULT_QUIRKS,
+        // draws the font glyphs 0 to 3 next to each other, then spins on 1212
+        program: &[0x60, 0x00, 0x61, 0x00, 0x62, 0x00, 0xF2, 0x29, 0xD0, 0x15, 0x70, 0x05, 0x72, 0x01, 0x32, 0x04,
+            0x12, 0x06, 0x12, 0x12],
+        cycles: 100,
+        check: |c| c.display_hash() == 0xfafb_0219_fcef_167f
+    },
+    MicroTest {
+        name: "Fx75/Fx85 save and restore flags",
+        quirks: DEFAULT_QUIRKS,
+        // saves V0-V2 to the flags, clobbers them and restores them
+        program: &[0x60, 0x11, 0x61, 0x22, 0x62, 0x33, 0xF2, 0x75, 0x60, 0x00, 0x61, 0x00, 0x62, 0x00, 0xF2, 0x85],
+        cycles: 8,
+        check: |c| c.get_register(0) == 0x11 && c.get_register(1) == 0x22 && c.get_register(2) == 0x33
+            && c.rpl_flags() == [0x11, 0x22, 0x33, 0, 0, 0, 0, 0]
+    },
+    MicroTest {
+        name: "Fx75 caps x at 7",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x67, 0x07, 0x68, 0x08, 0xFF, 0x75, 0x67, 0x00, 0x68, 0x00, 0xFF, 0x85],
+        cycles: 6,
+        check: |c| c.get_register(7) == 7 && c.get_register(8) == 0 && c.rpl_flags()[7] == 7
+    },
+    MicroTest {
+        name: "F201 Dxyn draws into plane 2 only",
+        quirks: DEFAULT_QUIRKS,
+        // draws font glyph 0 in plane 1, then the same glyph one pixel to the right in plane 2, plane 1 keeps its glyph
+        program: &[0xF0, 0x29, 0xD0, 0x05, 0xF2, 0x01, 0x61, 0x01, 0xD1, 0x05],
+        cycles: 5,
+        check: |c| c.planes() == 2 && c.get_register(0xF) == 0
+            && c.get_display(0) == 1 && c.get_display(1) == 3 && c.get_display(3) == 3 && c.get_display(4) == 2
+            && c.get_display(VIDEO_WIDTH) == 1 && c.get_display(VIDEO_WIDTH + 1) == 2
+            && c.get_display(VIDEO_WIDTH + 3) == 1 && c.get_display(VIDEO_WIDTH + 4) == 2
+    },
+    MicroTest {
+        name: "F201 00E0 leaves plane 1 alone",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0xF0, 0x29, 0xF3, 0x01, 0xD0, 0x05, 0xF2, 0x01, 0x00, 0xE0],
+        cycles: 5,
+        check: |c| displays_glyph(c, &[0xF0, 0x90, 0x90, 0x90, 0xF0])
+    },
+    MicroTest {
+        name: "00FF switches to 128x64",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x00, 0xFF],
+        cycles: 1,
+        check: |c| c.is_hires() && c.display_dimensions() == (128, 64) && c.display().len() == 128 * 64
+    },
+    MicroTest {
+        name: "00FE switches back to 64x32",
+        quirks: DEFAULT_QUIRKS,
+        program: &[0x00, 0xFF, 0x00, 0xFE],
+        cycles: 2,
+        check: |c| !c.is_hires() && c.display_dimensions() == (VIDEO_WIDTH, VIDEO_HEIGHT)
+    },
+    MicroTest {
+        name: "Dxy0 draws 16x16 in hi-res",
+        quirks: DEFAULT_QUIRKS,
+        // the sprite at 0x208 has the outermost pixels of its first row set and its last row full
+        program: &[0x00, 0xFF, 0xA2, 0x08, 0xD0, 0x00, 0x12, 0x06, 0x80, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF],
+        cycles: 4,
+        check: |c| c.get_display(0) == 1 && c.get_display(15) == 1 && c.get_display(16) == 0 && c.get_display(128 + 15) == 0
+            && (0..16).all(|column| c.get_display(15 * 128 + column) == 1) && c.get_display(15 * 128 + 16) == 0
+    }
+];
+
+// whether the top left corner of the display shows the given 8 pixel wide sprite rows
+pub(crate) fn displays_glyph(ch8: &Chip8, rows: &[u8]) -> bool {
+    rows.iter().enumerate().all(|(row, bits)| {
+        (0..8).all(|column| ch8.get_display(row * VIDEO_WIDTH + column) == ((bits >> (7 - column)) & 1) as u32)
+    })
+}
+
+fn run(test: &MicroTest) -> bool {
+    let mut ch8 = Chip8::default();
+    ch8.set_quirks(test.quirks);
+    ch8.set_seed(0);
+
+    if ch8.load_rom_bytes(test.program).is_err() {
+        return false;
+    }
+
+    // a fault halts the machine, which is what the overflow and end of memory tests check for
+    let _ = ch8.run_cycles(test.cycles);
+
+    (test.check)(&ch8)
+}
+
+// runs every micro test and prints a pass/fail matrix, returns whether all tests passed
+pub fn compat_report() -> bool {
+    let mut passed = 0;
+
+    for test in MICRO_TESTS {
+        let result = run(test);
+        if result {
+            passed += 1;
+        }
+
+        println!("{:<36} {}", test.name, if result { "pass" } else { "FAIL" });
+    }
+
+    println!("{}/{} passed", passed, MICRO_TESTS.len());
+
+    passed == MICRO_TESTS.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the same matrix --compat-report prints, so a failing opcode or quirk also fails cargo test
+    #[test]
+    fn micro_tests_pass() {
+        let failed: Vec<&str> = MICRO_TESTS.iter().filter(|test| !run(test)).map(|test| test.name).collect();
+
+        assert!(failed.is_empty(), "failing micro tests: {:?}", failed);
+    }
+}
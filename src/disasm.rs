@@ -0,0 +1,154 @@
+use crate::ch8::Chip8;
+use crate::opcode::{Opcode, decode};
+
+// mnemonic for a single opcode, unknown opcodes are shown as a raw data word
+pub fn mnemonic(opcode: u16) -> String {
+    match decode(opcode) {
+        Opcode::Cls => String::from("CLS"),
+        Opcode::Ret => String::from("RET"),
+        Opcode::ScrollDown(n) => std::format!("SCD {}", n),
+        Opcode::ScrollRight => String::from("SCR"),
+        Opcode::ScrollLeft => String::from("SCL"),
+        Opcode::LoRes => String::from("LOW"),
+        Opcode::HiRes => String::from("HIGH"),
+        Opcode::Sys(address) => std::format!("SYS {:#05X}", address),
+        Opcode::Jmp(address) => std::format!("JP {:#05X}", address),
+        Opcode::Call(address) => std::format!("CALL {:#05X}", address),
+        Opcode::SeVal(x, value) => std::format!("SE V{:X}, {:#04X}", x, value),
+        Opcode::SneVal(x, value) => std::format!("SNE V{:X}, {:#04X}", x, value),
+        Opcode::SeReg(x, y) => std::format!("SE V{:X}, V{:X}", x, y),
+        Opcode::LdReg(x, value) => std::format!("LD V{:X}, {:#04X}", x, value),
+        Opcode::AddVal(x, value) => std::format!("ADD V{:X}, {:#04X}", x, value),
+        Opcode::Copy(x, y) => std::format!("LD V{:X}, V{:X}", x, y),
+        Opcode::Or(x, y) => std::format!("OR V{:X}, V{:X}", x, y),
+        Opcode::And(x, y) => std::format!("AND V{:X}, V{:X}", x, y),
+        Opcode::Xor(x, y) => std::format!("XOR V{:X}, V{:X}", x, y),
+        Opcode::AddReg(x, y) => std::format!("ADD V{:X}, V{:X}", x, y),
+        Opcode::SubRegXY(x, y) => std::format!("SUB V{:X}, V{:X}", x, y),
+        Opcode::ShiftR(x, y) => std::format!("SHR V{:X}, V{:X}", x, y),
+        Opcode::SubRegYX(x, y) => std::format!("SUBN V{:X}, V{:X}", x, y),
+        Opcode::ShiftL(x, y) => std::format!("SHL V{:X}, V{:X}", x, y),
+        Opcode::SneReg(x, y) => std::format!("SNE V{:X}, V{:X}", x, y),
+        Opcode::LdIndx(address) => std::format!("LD I, {:#05X}", address),
+        Opcode::JmpAdd(address) => std::format!("JP V0, {:#05X}", address),
+        Opcode::RandAnd(x, value) => std::format!("RND V{:X}, {:#04X}", x, value),
+        Opcode::DrawPixel(x, y, height) => std::format!("DRW V{:X}, V{:X}, {}", x, y, height),
+        Opcode::SeKp(x) => std::format!("SKP V{:X}", x),
+        Opcode::SneKp(x) => std::format!("SKNP V{:X}", x),
+        Opcode::GetDelay(x) => std::format!("LD V{:X}, DT", x),
+        Opcode::WaitKey(x) => std::format!("LD V{:X}, K", x),
+        Opcode::SetDelay(x) => std::format!("LD DT, V{:X}", x),
+        Opcode::SetSound(x) => std::format!("LD ST, V{:X}", x),
+        Opcode::AddIndx(x) => std::format!("ADD I, V{:X}", x),
+        Opcode::GetFontChr(x) => std::format!("LD F, V{:X}", x),
+        Opcode::EncodeSave(x) => std::format!("LD B, V{:X}", x),
+        Opcode::Save(x) => std::format!("LD [I], V{:X}", x),
+        Opcode::Load(x) => std::format!("LD V{:X}, [I]", x),
+        Opcode::Plane(n) => std::format!("PLANE {}", n),
+        Opcode::SaveFlags(x) => std::format!("LD R, V{:X}", x),
+        Opcode::LoadFlags(x) => std::format!("LD V{:X}, R", x),
+        Opcode::Unknown(opcode) => std::format!("DW {:#06X}", opcode)
+    }
+}
+
+// decodes every 2 byte word of a rom loaded at base into (address, opcode, mnemonic)
+// data mixed into the code is decoded like any other word, a trailing odd byte is listed as a data byte
+pub fn disassemble(bytes: &[u8], base: u16) -> Vec<(u16, u16, String)> {
+    bytes.chunks(2).enumerate().map(|(ii, word)| {
+        let address = base.wrapping_add(ii as u16 * 2);
+
+        match word {
+            [high, low] => {
+                let opcode = (u16::from(*high) << 8) | u16::from(*low);
+                (address, opcode, mnemonic(opcode))
+            }
+            _ => (address, u16::from(word[0]), std::format!("DB {:#04X}", word[0]))
+        }
+    }).collect()
+}
+
+// disassembly of the instructions around the program counter, the next instruction is marked with an arrow
+pub fn listing(ch8: &Chip8, lines_before: usize, lines_after: usize) -> String {
+    let pc = ch8.pc_usize();
+    let mut str = String::new();
+
+    let mut address = pc.saturating_sub(lines_before * 2);
+    while address <= pc + lines_after * 2 && address + 1 < ch8.memory_size() {
+        let opcode = (u16::from(ch8.get_memory(address)) << 8) | u16::from(ch8.get_memory(address + 1));
+        let marker = if address == pc { "->" } else { "  " };
+
+        str += std::format!("{} {:#05X}: {:04X}  {}\n", marker, address, opcode, mnemonic(opcode)).as_str();
+        address += 2;
+    }
+
+    str
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_mnemonics() {
+        let table = [
+            (0x00E0, "CLS"),
+            (0x00EE, "RET"),
+            (0x00C7, "SCD 7"),
+            (0x00FB, "SCR"),
+            (0x00FC, "SCL"),
+            (0x00FE, "LOW"),
+            (0x00FF, "HIGH"),
+            (0x0123, "SYS 0x123"),
+            (0x1ABC, "JP 0xABC"),
+            (0x2ABC, "CALL 0xABC"),
+            (0x3A42, "SE VA, 0x42"),
+            (0x4A42, "SNE VA, 0x42"),
+            (0x5AB0, "SE VA, VB"),
+            (0x6A05, "LD VA, 0x05"),
+            (0x7A42, "ADD VA, 0x42"),
+            (0x8AB0, "LD VA, VB"),
+            (0x8AB1, "OR VA, VB"),
+            (0x8AB2, "AND VA, VB"),
+            (0x8AB3, "XOR VA, VB"),
+            (0x8AB4, "ADD VA, VB"),
+            (0x8AB5, "SUB VA, VB"),
+            (0x8AB6, "SHR VA, VB"),
+            (0x8AB7, "SUBN VA, VB"),
+            (0x8ABE, "SHL VA, VB"),
+            (0x9AB0, "SNE VA, VB"),
+            (0xA00F, "LD I, 0x00F"),
+            (0xBABC, "JP V0, 0xABC"),
+            (0xCA42, "RND VA, 0x42"),
+            (0xDAB5, "DRW VA, VB, 5"),
+            (0xEA9E, "SKP VA"),
+            (0xEAA1, "SKNP VA"),
+            (0xFA07, "LD VA, DT"),
+            (0xFA0A, "LD VA, K"),
+            (0xFA15, "LD DT, VA"),
+            (0xFA18, "LD ST, VA"),
+            (0xFA1E, "ADD I, VA"),
+            (0xFA29, "LD F, VA"),
+            (0xFA33, "LD B, VA"),
+            (0xFA55, "LD [I], VA"),
+            (0xFA65, "LD VA, [I]"),
+            (0xF201, "PLANE 2"),
+            (0xFA75, "LD R, VA"),
+            (0xFA85, "LD VA, R"),
+            (0x8ABF, "DW 0x8ABF")
+        ];
+
+        for (opcode, expected) in &table {
+            assert_eq!(mnemonic(*opcode), *expected, "{:04X}", opcode);
+        }
+    }
+
+    // addresses count up from the base, a trailing odd byte is data
+    #[test]
+    fn disassembles_words() {
+        assert_eq!(disassemble(&[0x00, 0xE0, 0x12, 0x00, 0xAB], 0x200), [
+            (0x200, 0x00E0, String::from("CLS")),
+            (0x202, 0x1200, String::from("JP 0x200")),
+            (0x204, 0x00AB, String::from("DB 0xAB"))
+        ]);
+    }
+}
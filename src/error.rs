@@ -0,0 +1,26 @@
+use thiserror::Error;
+
+// failures of the emulator core, faults while running also halt the machine
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum Ch8Error {
+    #[error("rom not found: {0}")]
+    RomNotFound(String),
+    #[error("failed to read rom: {0}")]
+    RomUnreadable(String),
+    #[error("rom is too big to load into memory ({0} bytes)")]
+    RomTooLarge(usize),
+    #[error("unsupported instruction {0:#06X}")]
+    InvalidOpcode(u16),
+    #[error("stack overflow, more than 16 nested calls")]
+    StackOverflow,
+    #[error("stack underflow, return without a call")]
+    StackUnderflow,
+    #[error("pc {0:#05X} is at the end of memory")]
+    PcOutOfRange(u16),
+    #[error("pc {0:#05X} ran past the end of the rom")]
+    PastRomEnd(u16),
+    #[error("invalid key {0:#X}, expected 0 - F")]
+    InvalidKey(u8),
+    #[error("key {0:X} is released at instruction {2} before it is pressed at {1}")]
+    ReleaseBeforePress(u8, u64, u64)
+}
@@ -0,0 +1,185 @@
+use anyhow::{Context, Error, Result, bail};
+use sdl2::keyboard::Keycode;
+use std::collections::BTreeMap;
+use std::fs;
+use std::str::FromStr;
+
+use ch8_rs::ch8::Chip8;
+
+// hex keys in the order they are laid out on the cosmac vip keypad
+static KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF]
+];
+
+// maps keyboard keys to the 16 chip8 keys (0x0 - 0xF)
+pub struct Keymap {
+    bindings: Vec<(Keycode, u8)>
+}
+
+impl Keymap {
+    pub fn new(bindings: Vec<(Keycode, u8)>) -> Self {
+        Self { bindings }
+    }
+
+    // reads a json object of sdl key names to chip8 keys, e.g. { "Space": 5, "Q": 4, "D": 6 }
+    pub fn load(path: &str) -> Result<Self> {
+        let json = fs::read_to_string(path).context("failed to read keymap")?;
+
+        Self::parse(&json)
+    }
+
+    // key names are case insensitive, so "q" and "Q" bind the same keyboard key and can't both be used
+    fn parse(json: &str) -> Result<Self> {
+        let entries: BTreeMap<String, u8> = serde_json::from_str(json).context("invalid keymap, expected an object of key names to 0 - 15")?;
+
+        let mut bindings: Vec<(Keycode, u8)> = Vec::new();
+        for (name, key) in entries {
+            let keycode = Keycode::from_name(&name).with_context(|| std::format!("unknown key name '{}' in keymap", name))?;
+            if key > 0xF {
+                bail!("invalid chip8 key {} for '{}' in keymap, expected 0 - 15", key, name);
+            }
+
+            if bindings.iter().any(|(k, _)| *k == keycode) {
+                bail!("key '{}' is bound more than once in keymap", name);
+            }
+
+            bindings.push((keycode, key));
+        }
+
+        Ok(Self::new(bindings))
+    }
+
+    pub fn key(&self, keycode: Keycode) -> Option<u8> {
+        self.bindings.iter().find(|(k, _)| *k == keycode).map(|(_, key)| *key)
+    }
+
+    // the keypad as a 4x4 grid showing which keyboard key is bound to each chip8 key
+    pub fn grid(&self) -> String {
+        let mut str = String::new();
+
+        for row in KEYPAD_LAYOUT.iter() {
+            for key in row.iter() {
+                let name = self.bindings.iter().find(|(_, k)| k == key).map_or(String::from("-"), |(keycode, _)| keycode.name());
+                str += std::format!("{:X}: {:<8}", key, name).as_str();
+            }
+
+            str += "\n";
+        }
+
+        str
+    }
+}
+
+impl Default for Keymap {
+    // the cosmac vip keypad on the left of a qwerty keyboard, 1234/QWER/ASDF/ZXCV
+    fn default() -> Self {
+        Self::new(vec![
+            (Keycode::Num1, 0x1), (Keycode::Num2, 0x2), (Keycode::Num3, 0x3), (Keycode::Num4, 0xC),
+            (Keycode::Q, 0x4), (Keycode::W, 0x5), (Keycode::E, 0x6), (Keycode::R, 0xD),
+            (Keycode::A, 0x7), (Keycode::S, 0x8), (Keycode::D, 0x9), (Keycode::F, 0xE),
+            (Keycode::Z, 0xA), (Keycode::X, 0x0), (Keycode::C, 0xB), (Keycode::V, 0xF)
+        ])
+    }
+}
+
+// how physical key presses reach the keypad
+#[derive(Clone, Copy, PartialEq)]
+pub enum InputMode {
+    Level, // the key reads as pressed for as long as it is held
+    Latch  // every physical press reads as pressed once, holding it doesn't repeat
+}
+
+impl InputMode {
+    pub fn key_down(&self, ch8: &mut Chip8, key: u8, repeat: bool) {
+        match self {
+            InputMode::Level => ch8.set_input(key, true),
+            InputMode::Latch if !repeat => {
+                // the press stays latched until an instruction reads it, the key itself is released right away
+                ch8.set_input(key, true);
+                ch8.set_input(key, false);
+            }
+            InputMode::Latch => { }
+        }
+    }
+
+    pub fn key_up(&self, ch8: &mut Chip8, key: u8) {
+        ch8.set_input(key, false);
+    }
+}
+
+impl FromStr for InputMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "level" => Ok(InputMode::Level),
+            "latch" => Ok(InputMode::Latch),
+            _ => bail!("unknown input mode '{}', expected level or latch", s)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bindings() {
+        let keymap = Keymap::parse(r#"{ "Space": 5, "Q": 4, "d": 6 }"#).unwrap();
+
+        assert_eq!(keymap.key(Keycode::Space), Some(5));
+        assert_eq!(keymap.key(Keycode::Q), Some(4));
+        assert_eq!(keymap.key(Keycode::D), Some(6));
+        assert_eq!(keymap.key(Keycode::W), None);
+    }
+
+    #[test]
+    fn rejects_duplicate_bindings() {
+        let err = Keymap::parse(r#"{ "Q": 4, "q": 5 }"#).err().unwrap();
+        assert!(err.to_string().contains("bound more than once"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_unknown_bindings() {
+        let err = Keymap::parse(r#"{ "NoSuchKey": 4 }"#).err().unwrap();
+        assert!(err.to_string().contains("unknown key name 'NoSuchKey'"), "{}", err);
+
+        let err = Keymap::parse(r#"{ "Q": 16 }"#).err().unwrap();
+        assert!(err.to_string().contains("invalid chip8 key 16"), "{}", err);
+
+        assert!(Keymap::parse(r#"{ "Q": "four" }"#).is_err());
+    }
+
+    // whether key 5 reads as down on two ExA1 in a row, the key is pressed before the first and held or released before the second
+    fn reads_down(mode: InputMode, hold: bool) -> (bool, bool) {
+        // V0 = 5, then twice: V1 += 1 unless key V0 is up
+        let mut ch8 = Chip8::default();
+        ch8.load_rom_bytes(&[0x60, 0x05, 0xE0, 0xA1, 0x71, 0x01, 0xE0, 0xA1, 0x71, 0x01]).unwrap();
+        ch8.cycle().unwrap();
+
+        mode.key_down(&mut ch8, 5, false);
+        ch8.run_cycles(2).unwrap();
+        let first = ch8.get_register(1);
+
+        if hold {
+            mode.key_down(&mut ch8, 5, true); // the keyboard's auto repeat
+        } else {
+            mode.key_up(&mut ch8, 5);
+        }
+        ch8.run_cycles(2).unwrap();
+
+        (first == 1, ch8.get_register(1) > first)
+    }
+
+    // a held key reads as down on every ExA1 in level mode and only on the first one in latch mode, key repeats included
+    #[test]
+    fn level_and_latch_modes() {
+        assert_eq!(reads_down(InputMode::Level, true), (true, true));
+        assert_eq!(reads_down(InputMode::Latch, true), (true, false));
+        assert_eq!(reads_down(InputMode::Level, false), (true, false));
+        assert_eq!(reads_down(InputMode::Latch, false), (true, false));
+    }
+}
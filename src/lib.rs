@@ -0,0 +1,24 @@
+// the emulator core without the sdl frontend, shared by the desktop emulator in main.rs, the browser bindings in wasm.rs
+// and anything else that wants to run chip8 roms, e.g.
+//
+//     let mut ch8 = ch8_rs::Chip8::new();
+//     ch8.load_rom_bytes(&rom)?;
+//     ch8.run_frame()?;
+pub mod ch8;
+pub mod compat;
+pub mod disasm;
+pub mod error;
+pub mod opcode;
+pub mod render;
+pub mod rewind;
+pub mod savestate;
+pub mod trace;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use ch8::{Chip8, Chip8Builder, Chip8State, QUIRK_FLAGS, QuirkFlag, Quirks, StateDiff, WatchHit};
+pub use ch8::{FONT_START, HIRES_VIDEO_HEIGHT, HIRES_VIDEO_WIDTH, INSTRUCTION_RATE, MAX_MEMORY_SIZE, MEMORY_SIZE, ROM_START, TIMER_RATE, VIDEO_HEIGHT, VIDEO_WIDTH};
+pub use error::Ch8Error;
+pub use opcode::{DecodedInstr, Opcode};
+pub use render::{NullRenderer, Renderer};
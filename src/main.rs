@@ -1,29 +1,105 @@
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
 use std::env;
-use std::ffi::c_void;
+use std::io::{self, Write};
 use std::path::Path;
-use std::ptr::null;
 
 extern crate sdl2;
-use sdl2::pixels::{PixelFormatEnum};
+use sdl2::audio::{AudioDevice, AudioSpecDesired, AudioStatus};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::sys::{SDL_UpdateTexture};
 
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-mod ch8;
-use ch8::Chip8;
-use ch8::{VIDEO_HEIGHT, VIDEO_WIDTH};
+use env_logger::Target;
+use log::{LevelFilter, warn};
+
+use ch8_rs::{Chip8, Chip8Builder, Chip8State};
+use ch8_rs::compat;
+use ch8_rs::{INSTRUCTION_RATE, MEMORY_SIZE, ROM_START, TIMER_RATE, VIDEO_HEIGHT, VIDEO_WIDTH};
+use ch8_rs::disasm;
+use ch8_rs::render::{BACKGROUND, FOREGROUND, PLANE_2, PLANES_BOTH, RenderMode, Renderer, TerminalRenderer, parse_rgb, render_argb};
+use ch8_rs::rewind::RewindBuffer;
+use ch8_rs::trace::{TraceFilter, TraceFormat};
+
+mod audio;
+use audio::SquareWave;
+
+mod bench;
+
+mod keymap;
+use keymap::{InputMode, Keymap};
+
+mod overlay;
+use overlay::{GhostTrail, Mirror, SmartDraw, hsv_to_argb, scanline_alpha, scanline_rows};
+
+mod record;
+use record::{GIF_FRAME_INTERVAL, GIF_MAX_FRAMES, GifRecorder, RawVideo};
+
+mod quirkfuzz;
+
+mod screenshot;
 
 mod tickrate;
-use tickrate::Tickrate;
+use tickrate::{FpsLog, Tickrate};
+
+mod wizard;
+
+static STEP_OVER_LIMIT: usize = 1_000_000; // cycles a stepped over subroutine gets to return
+static BENCHMARK_DURATION: Duration = Duration::from_secs(3); // time each rom runs for in --benchmark-suite
+static RECORD_RATE: u32 = 60; // frames per second written by --record-video-raw
+static ATTRACT_IDLE: Duration = Duration::from_secs(10); // time without input before the palette starts cycling
 
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
     let mut rom = "";
+    let mut roms: Vec<&str> = Vec::new();
     let mut is_debug: bool = false;
     let mut is_step_mode: bool = false;
+    let mut is_start_paused: bool = false;
+    let mut is_verbose_rom_load: bool = false;
+    let mut is_compat_report: bool = false;
+    let mut is_no_font: bool = false;
+    let mut is_strict: bool = false;
+    let mut is_halt_on_rom_end: bool = false;
+    let mut is_keymap_print: bool = false;
+    let mut keymap_path: Option<&str> = None;
+    let mut breakpoints: Vec<u16> = Vec::new();
+    let mut watchpoints: Vec<u16> = Vec::new();
+    let mut is_benchmark_suite: bool = false;
+    let mut is_quirk_fuzzer: bool = false;
+    let mut expected_hash: Option<u64> = None;
+    let mut trace_format = TraceFormat::Text;
+    let mut trace_filter: Option<TraceFilter> = None;
+    let mut input_mode = InputMode::Level;
+    let mut ghost_frames: usize = 0;
+    let mut rewind_seconds: usize = 10;
+    let mut mirror = Mirror::None;
+    let mut render_mode = RenderMode::Sdl;
+    let mut scanlines: u8 = 0;
+    let mut is_draw_grid: bool = false;
+    let mut is_smart_draw: bool = false;
+    let mut fps_log: Option<FpsLog> = None;
+    let mut raw_video: Option<RawVideo> = None;
+    let mut gif_path: Option<&str> = None;
+    let mut is_palette_cycle: bool = false;
+    let mut is_visual_beep: bool = false;
+    let mut is_beep_indicator: bool = false;
+    let mut beep_hz: f32 = 440.0;
+    let mut volume: f32 = 0.2;
+    let mut is_interactive_quirks: bool = false;
+    let mut is_dump_fontset: bool = false;
+    let mut is_disasm: bool = false;
+    let mut memory_size = MEMORY_SIZE;
+    let mut seed: Option<u64> = None;
+    let mut cycles_per_second = INSTRUCTION_RATE;
+    let mut foreground = FOREGROUND;
+    let mut background = BACKGROUND;
+    let mut memory_png: Option<&str> = None;
+    let mut rpl_file: Option<&str> = None;
     let mut tr = Tickrate::new();
 
     let mut iter = args.iter().skip(1);
@@ -36,24 +112,329 @@ fn main() -> Result<()> {
             is_step_mode = true;
         }
 
+        if ii.eq("--break") {
+            let address = iter.next().context("--break requires an address")?;
+            breakpoints.push(u16::from_str_radix(address.trim_start_matches("0x"), 16).context("invalid --break address, expected hex")?);
+        }
+
+        if ii.eq("--watch") {
+            let address = iter.next().context("--watch requires an address")?;
+            watchpoints.push(u16::from_str_radix(address.trim_start_matches("0x"), 16).context("invalid --watch address, expected hex")?);
+        }
+
+        if ii.eq("--start-paused") {
+            is_start_paused = true;
+        }
+
+        if ii.eq("--verbose-rom-load") {
+            is_verbose_rom_load = true;
+        }
+
+        if ii.eq("--benchmark-suite") {
+            is_benchmark_suite = true;
+        }
+
+        if ii.eq("--compat-quirk-fuzzer") {
+            is_quirk_fuzzer = true;
+        }
+
+        if ii.eq("--expect-hash") {
+            let hash = iter.next().context("--expect-hash requires a hash")?;
+            expected_hash = Some(u64::from_str_radix(hash, 16).context("invalid --expect-hash, expected a hex hash")?);
+        }
+
+        if ii.eq("--keymap") {
+            keymap_path = Some(iter.next().context("--keymap requires a file")?);
+        }
+
+        if ii.eq("--keymap-print") {
+            is_keymap_print = true;
+        }
+
+        if ii.eq("--halt-on-rom-end") {
+            is_halt_on_rom_end = true;
+        }
+
+        if ii.eq("--strict") {
+            is_strict = true;
+        }
+
+        if ii.eq("--no-font") {
+            is_no_font = true;
+        }
+
+        if ii.eq("--disasm") {
+            is_disasm = true;
+        }
+
+        if ii.eq("--dump-fontset") {
+            is_dump_fontset = true;
+        }
+
+        if ii.eq("--compat-report") {
+            is_compat_report = true;
+        }
+
+        if ii.eq("--trace-format") {
+            trace_format = iter.next().context("--trace-format requires a value")?.parse()?;
+        }
+
+        if ii.eq("--interactive-quirks") {
+            is_interactive_quirks = true;
+        }
+
+        if ii.eq("--input-mode") {
+            input_mode = iter.next().context("--input-mode requires a value")?.parse()?;
+        }
+
+        if ii.eq("--trace-filter") {
+            trace_filter = Some(iter.next().context("--trace-filter requires a list of instructions")?.parse()?);
+        }
+
+        if ii.eq("--beep-hz") {
+            beep_hz = iter.next().context("--beep-hz requires a frequency")?.parse().context("invalid --beep-hz frequency")?;
+        }
+
+        if ii.eq("--volume") {
+            volume = iter.next().context("--volume requires a value")?.parse().context("invalid --volume, expected 0.0 - 1.0")?;
+            if !(0.0..=1.0).contains(&volume) {
+                bail!("invalid --volume, expected 0.0 - 1.0");
+            }
+        }
+
+        if ii.eq("--visual-beep") {
+            is_visual_beep = true;
+        }
+
+        if ii.eq("--beep-indicator") {
+            is_beep_indicator = true;
+        }
+
+        if ii.eq("--palette-cycle") {
+            is_palette_cycle = true;
+        }
+
+        if ii.eq("--fg") {
+            foreground = parse_rgb(iter.next().context("--fg requires a color")?)?;
+        }
+
+        if ii.eq("--bg") {
+            background = parse_rgb(iter.next().context("--bg requires a color")?)?;
+        }
+
+        if ii.eq("--cycles-per-second") {
+            cycles_per_second = iter.next().context("--cycles-per-second requires a rate")?.parse().context("invalid --cycles-per-second rate")?;
+            if cycles_per_second == 0 {
+                bail!("invalid --cycles-per-second rate, expected at least 1");
+            }
+        }
+
+        if ii.eq("--seed") {
+            seed = Some(iter.next().context("--seed requires a value")?.parse().context("invalid --seed value")?);
+        }
+
+        if ii.eq("--memory-size") {
+            memory_size = iter.next().context("--memory-size requires a value")?.parse().context("invalid --memory-size value")?;
+        }
+
+        if ii.eq("--memory-png") {
+            memory_png = Some(iter.next().context("--memory-png requires a file")?);
+        }
+
+        if ii.eq("--rpl-file") {
+            rpl_file = Some(iter.next().context("--rpl-file requires a file")?);
+        }
+
+        if ii.eq("--rewind-seconds") {
+            rewind_seconds = iter.next().context("--rewind-seconds requires a value")?.parse().context("invalid --rewind-seconds value")?;
+        }
+
+        if ii.eq("--record") {
+            gif_path = Some(iter.next().context("--record requires a gif path")?);
+        }
+
+        if ii.eq("--record-video-raw") {
+            raw_video = Some(RawVideo::new(RECORD_RATE));
+        }
+
+        if ii.eq("--fps-log") {
+            fps_log = Some(FpsLog::create(iter.next().context("--fps-log requires a file")?)?);
+        }
+
+        if ii.eq("--smart-draw") {
+            is_smart_draw = true;
+        }
+
+        if ii.eq("--draw-grid") {
+            is_draw_grid = true;
+        }
+
+        if ii.eq("--scanlines") {
+            scanlines = iter.next().context("--scanlines requires an intensity")?.parse().context("invalid --scanlines intensity, expected 0 - 100")?;
+            if scanlines > 100 {
+                bail!("invalid --scanlines intensity, expected 0 - 100");
+            }
+        }
+
+        if ii.eq("--render") {
+            render_mode = iter.next().context("--render requires a mode")?.parse()?;
+        }
+
+        if ii.eq("--mirror") {
+            mirror = iter.next().context("--mirror requires a mode")?.parse()?;
+        }
+
+        if ii.eq("--ghost") {
+            ghost_frames = iter.next().context("--ghost requires a frame count")?.parse().context("invalid --ghost frame count")?;
+        }
+
         if !ii.starts_with("--") {
             rom = ii;
+            roms.push(ii);
+        }
+    }
+
+    let keymap = match keymap_path {
+        Some(path) => Keymap::load(path)?,
+        None => Keymap::default()
+    };
+
+    if is_keymap_print {
+        print!("{}", keymap.grid());
+        return Ok(());
+    }
+
+    if is_dump_fontset {
+        print!("{}", Chip8::default().dump_fontset());
+        return Ok(());
+    }
+
+    if is_compat_report {
+        if !compat::compat_report() {
+            bail!("compatibility report has failing tests");
         }
+
+        return Ok(());
     }
 
-    if rom == "" {
+    // --debug turns on the per instruction trace, printed bare on stdout so json traces stay parseable, RUST_LOG still overrides the level
+    // raw video owns stdout, the log moves to stderr then, like every status line of the window
+    env_logger::Builder::new()
+        .filter_level(if is_debug { LevelFilter::Trace } else { LevelFilter::Warn })
+        .format(|buf, record| writeln!(buf, "{}", record.args()))
+        .target(if raw_video.is_some() { Target::Stderr } else { Target::Stdout })
+        .parse_default_env()
+        .init();
+
+    if is_disasm {
+        if rom.is_empty() {
+            bail!("usage: ./ch8-rs --disasm <rom>");
+        }
+
+        let bytes = std::fs::read(rom).context("invalid rom path supplied")?;
+        for (address, opcode, mnemonic) in disasm::disassemble(&bytes, ROM_START as u16) {
+            println!("{:#05X}: {:04X}  {}", address, opcode, mnemonic);
+        }
+
+        return Ok(());
+    }
+
+    if is_quirk_fuzzer {
+        if rom.is_empty() {
+            bail!("usage: ./ch8-rs --compat-quirk-fuzzer [optional: --expect-hash <hash>] <rom>");
+        }
+
+        return quirkfuzz::quirk_fuzzer(rom, expected_hash);
+    }
+
+    if is_benchmark_suite {
+        if roms.is_empty() {
+            bail!("usage: ./ch8-rs --benchmark-suite <rom> [<rom> ...]");
+        }
+
+        return bench::benchmark_suite(&roms, BENCHMARK_DURATION);
+    }
+
+    if rom.is_empty() {
         bail!("usage: ./ch8-rs [optional: --debug] <path_to_rom_file>")
     }
 
-    let mut ch8 = Chip8::new(is_debug);
-    match ch8.read_rom(rom) {
-        Err(s) => bail!(s), // early exit if read fails
-        Ok(()) => { }
+    let mut builder = Chip8Builder::new().clock_hz(cycles_per_second);
+    if let Some(seed) = seed {
+        builder = builder.seed(seed);
+    }
+
+    let mut ch8 = builder.build();
+    ch8.set_trace_format(trace_format);
+    ch8.set_trace_filter(trace_filter);
+    ch8.set_strict(is_strict);
+    ch8.set_halt_on_rom_end(is_halt_on_rom_end);
+    ch8.set_memory_size(memory_size)?;
+
+    for address in breakpoints {
+        ch8.add_breakpoint(address);
+    }
+
+    for address in watchpoints {
+        ch8.add_watchpoint(address);
+    }
+
+    if is_no_font {
+        ch8.unload_font();
+    }
+
+    if let Err(s) = ch8.read_rom(rom) {
+        bail!(s); // early exit if read fails
+    }
+
+    if is_interactive_quirks {
+        ch8.set_quirks(wizard::interactive_quirks(rom, ch8.quirks())?);
+    }
+
+    if is_verbose_rom_load {
+        print!("{}", ch8.memory_map());
+    }
+
+    if let Some(path) = memory_png {
+        return screenshot::save_memory_png(&ch8, path);
+    }
+
+    // a missing file just means no flags were saved yet
+    if let Some(path) = rpl_file.filter(|path| Path::new(path).exists()) {
+        ch8.load_rpl_flags(Path::new(path))?;
+    }
+
+    let mut gif = gif_path.map(|_| GifRecorder::new(GIF_FRAME_INTERVAL, GIF_MAX_FRAMES));
+
+    if render_mode == RenderMode::Terminal {
+        run_terminal(&mut ch8, gif.as_mut())?;
+
+        if let Some(path) = rpl_file {
+            ch8.save_rpl_flags(Path::new(path))?;
+        }
+
+        if let (Some(gif), Some(path)) = (gif, gif_path) {
+            gif.save(path)?;
+            println!("saved {} frames to {}", gif.frame_count(), path);
+        }
+
+        return Ok(());
     }
 
     let sdl_ctx= sdl2::init().unwrap();
     let video = sdl_ctx.video().unwrap();
+
+    // opened once and only paused/resumed with the sound timer, closed when it is dropped on exit
+    // machines without an audio device (headless, ssh, containers) run silently instead of failing
+    let audio_spec = AudioSpecDesired { freq: Some(44100), channels: Some(1), samples: None };
+    let beep: Option<AudioDevice<SquareWave>> = sdl_ctx.audio()
+        .and_then(|audio| audio.open_playback(None, &audio_spec, |spec| {
+            SquareWave { phase_inc: beep_hz / spec.freq as f32, phase: 0.0, volume }
+        }))
+        .map_err(|e| warn!("no audio, the beep is disabled: {}", e))
+        .ok();
     let filename = String::from(Path::new(rom).file_stem().unwrap().to_str().unwrap());
+    let state_path = String::from(Path::new(rom).with_extension("state").to_str().unwrap());
 
     let window = video.window(std::format!("ch8-rs - playing: {}", filename).as_str(), VIDEO_WIDTH as u32 * 15, VIDEO_HEIGHT as u32 * 15).position_centered().build().unwrap();
     let mut canvas = window.into_canvas().build().unwrap();
@@ -62,71 +443,452 @@ fn main() -> Result<()> {
     canvas.present();
 
     let texture_creator = canvas.texture_creator();
-    let output_texture = texture_creator.create_texture_streaming(Some(PixelFormatEnum::ARGB8888), 64, 32).unwrap();
+    let (width, height) = ch8.display_dimensions();
+    let mut renderer = SdlRenderer {
+        canvas,
+        texture_creator: &texture_creator,
+        texture: texture_creator.create_texture_streaming(Some(PixelFormatEnum::ARGB8888), width as u32, height as u32).unwrap(),
+        width,
+        height,
+        ghost_trail: GhostTrail::new(ghost_frames),
+        ghost_frames,
+        smart_draw: if is_smart_draw { Some(SmartDraw::new()) } else { None },
+        mirror,
+        scanlines,
+        draw_grid: is_draw_grid,
+        foreground,
+        background,
+        beeping: false,
+        beep_indicator: false,
+        display: vec![0; width * height],
+        frame: vec![background; width * height]
+    };
 
+    let mut rewind_buffer = RewindBuffer::with_seconds(rewind_seconds);
+    let mut rewinding = false;
+    let mut last_input = Instant::now();
     let mut advance = false;
+    let mut step_over = false;
+    let mut paused = is_start_paused;
+    let mut state_slot: Option<Chip8State> = None;
     let mut events = sdl_ctx.event_pump().unwrap();
+
+    if paused {
+        eprintln!("paused at {:#05X}, press P to resume", ch8.get_pc());
+    }
+
+    if is_step_mode || paused {
+        eprint!("{}", disasm::listing(&ch8, 4, 4));
+    }
+
+    // every iteration of the loop is one 60hz frame, paced against the wall clock so the speed doesn't depend on the host
+    let frame_period = Duration::from_secs(1) / TIMER_RATE;
+    let mut next_frame = Instant::now();
+
     'running: loop {
+        let mut toggle_pause = false;
+
         for event in events.poll_iter() {
+            if let Event::KeyDown { .. } = event {
+                last_input = Instant::now();
+            }
+
             match event {
                 Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), ..} => break 'running,
                 Event::KeyDown { keycode: Some(Keycode::Return), .. } => advance = true,
-                Event::KeyDown { keycode: Some(Keycode::F1), .. } => println!("{}", ch8.dump_registers()),
-                // pong
-                // Event::KeyDown { keycode: Some(Keycode::Z), .. } => ch8.set_input(1, true),
-                // Event::KeyUp { keycode: Some(Keycode::Z), .. } => ch8.set_input(1, false),
-                // Event::KeyDown { keycode: Some(Keycode::S), .. } => ch8.set_input(4, true),
-                // Event::KeyUp { keycode: Some(Keycode::S), .. } => ch8.set_input(4, false),
-                // Event::KeyDown { keycode: Some(Keycode::R), .. } => ch8.set_input(12, true),
-                // Event::KeyUp { keycode: Some(Keycode::R), .. } => ch8.set_input(12, false),
-                // Event::KeyDown { keycode: Some(Keycode::F), .. } => ch8.set_input(13, true),
-                // Event::KeyUp { keycode: Some(Keycode::F), .. } => ch8.set_input(13, false),
-
-                // space invaders
-                Event::KeyDown { keycode: Some(Keycode::Space), .. } => ch8.set_input(5, true),
-                Event::KeyUp { keycode: Some(Keycode::Space), .. } => ch8.set_input(5, false),
-                Event::KeyDown { keycode: Some(Keycode::Q), .. } => ch8.set_input(4, true),
-                Event::KeyUp { keycode: Some(Keycode::Q), .. } => ch8.set_input(4, false),
-                Event::KeyDown { keycode: Some(Keycode::D), .. } => ch8.set_input(6, true),
-                Event::KeyUp { keycode: Some(Keycode::D), .. } => ch8.set_input(6, false),
+                Event::KeyDown { keycode: Some(Keycode::F8), .. } => step_over = true,
+                Event::KeyDown { keycode: Some(Keycode::F5), .. } => {
+                    state_slot = Some(ch8.snapshot());
+                    eprintln!("saved state to slot");
+                }
+                Event::KeyDown { keycode: Some(Keycode::F9), .. } => match &state_slot {
+                    Some(state) => {
+                        ch8.restore(state);
+                        eprintln!("restored state from slot");
+                    }
+                    None => eprintln!("no state saved, press F5 first")
+                },
+                Event::KeyDown { keycode: Some(Keycode::F6), .. } => match ch8.save_state_to_file(Path::new(&state_path)) {
+                    Ok(()) => eprintln!("saved state to {}", state_path),
+                    Err(e) => eprintln!("{:#}", e)
+                },
+                Event::KeyDown { keycode: Some(Keycode::F7), .. } => match ch8.load_state_from_file(Path::new(&state_path)) {
+                    Ok(()) => eprintln!("loaded state from {}", state_path),
+                    Err(e) => eprintln!("{:#}", e)
+                },
+                Event::KeyDown { keycode: Some(Keycode::P), .. } => toggle_pause = true,
+                // space only pauses when the keymap doesn't hand it to the game
+                Event::KeyDown { keycode: Some(Keycode::Space), .. } if keymap.key(Keycode::Space).is_none() => toggle_pause = true,
+                Event::KeyDown { keycode: Some(Keycode::Backspace), .. } => rewinding = true,
+                Event::KeyUp { keycode: Some(Keycode::Backspace), .. } => rewinding = false,
+                Event::KeyDown { keycode: Some(Keycode::F1), .. } => eprint!("{}{}", ch8.dump_registers(), ch8.dump_stack()),
+                Event::KeyDown { keycode: Some(Keycode::F2), .. } => {
+                    let mut quirks = ch8.quirks();
+                    quirks.jump_uses_vx = !quirks.jump_uses_vx;
+                    ch8.set_quirks(quirks);
+                    eprintln!("quirk jump_uses_vx: {}", quirks.jump_uses_vx);
+                }
+                Event::KeyDown { keycode: Some(Keycode::F3), .. } if is_debug => edit_memory(&mut ch8),
+                Event::KeyDown { keycode: Some(Keycode::F4), .. } if is_debug => {
+                    ch8.freeze_timers(!ch8.timers_frozen());
+                    eprintln!("timers frozen: {}", ch8.timers_frozen());
+                }
+                Event::KeyDown { keycode: Some(Keycode::F10), .. } => {
+                    ch8.reset();
+                    eprintln!("reset {}", filename);
+                }
+                Event::KeyDown { keycode: Some(Keycode::F12), .. } => {
+                    // scaled by the same factor the window is, so the png looks like what is on screen
+                    let (window_width, _) = renderer.canvas.window().size();
+                    let scale = (window_width as usize / renderer.width).max(1);
+                    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+                    let path = std::format!("{}-{}.png", filename, timestamp);
+
+                    match screenshot::save_display_png(&ch8, &path, scale) {
+                        Ok(()) => eprintln!("saved screenshot to {}", path),
+                        Err(e) => eprintln!("{:#}", e)
+                    }
+                }
+                Event::KeyDown { keycode: Some(keycode), repeat, .. } => if let Some(key) = keymap.key(keycode) { input_mode.key_down(&mut ch8, key, repeat) },
+                Event::KeyUp { keycode: Some(keycode), .. } => if let Some(key) = keymap.key(keycode) { input_mode.key_up(&mut ch8, key) },
                 _ => { }
             }
         }
 
-        if is_step_mode && !advance {
-            continue;
-        } 
-
-        ch8.cycle();
+        if toggle_pause {
+            paused = !paused;
+            eprintln!("{}", if paused { "paused" } else { "resumed" });
+        }
 
-        if ch8.should_draw() {
-            ch8.set_should_draw(false);
-            let mut r: Vec<u32> = vec![0; 64 * 32];
+        if rewinding {
+            if let Some(state) = rewind_buffer.pop() {
+                ch8.restore(&state);
+            }
+        } else if is_step_mode || paused {
+            // while paused the emulator single steps like in step mode
+            if step_over {
+                match ch8.step_over(STEP_OVER_LIMIT) {
+                    Ok(true) => { }
+                    Ok(false) => eprintln!("subroutine did not return within {} cycles", STEP_OVER_LIMIT),
+                    Err(e) => eprintln!("{}, halting", e)
+                }
+            } else if advance {
+                if let Err(e) = ch8.cycle() {
+                    eprintln!("{}, halting", e);
+                }
+            }
 
-            for ii in 0..64 * 32 {
-                if ch8.get_display(ii as usize) == 0 {
-                    r[ii] = 0xFF000000;
-                } else {
-                    r[ii] = 0xFFFFFFFF;
+            if advance || step_over {
+                eprint!("{}", disasm::listing(&ch8, 4, 4));
+            }
+        } else if !ch8.breakpoints().is_empty() {
+            // a frame's worth of instructions, checking the pc after each of them, a hit pauses like P would
+            match ch8.run_until_breakpoint(ch8.cycles_per_frame() as usize) {
+                Ok(Some(address)) => {
+                    paused = true;
+                    eprintln!("breakpoint at {:#05X}, press P to continue", address);
+                    eprint!("{}", disasm::listing(&ch8, 4, 4));
                 }
+                Ok(None) => { }
+                Err(e) => eprintln!("{}, halting", e)
             }
+        } else if let Err(e) = ch8.run_frame() {
+            eprintln!("{}, halting", e);
+        }
+
+        let watch_hits = ch8.take_watch_hits();
+        for hit in &watch_hits {
+            eprintln!("watchpoint {:#05X} written by {:#05X}: {:#04X} -> {:#04X}", hit.address, hit.pc, hit.old, hit.new);
+        }
+
+        if !watch_hits.is_empty() && !paused {
+            paused = true;
+            eprintln!("paused, press P to continue");
+        }
+
+        // attract mode, slowly cycle the background hue while nobody is playing
+        let idle = last_input.elapsed();
+        let last_background = renderer.background;
+        renderer.background = if is_palette_cycle && idle >= ATTRACT_IDLE {
+            hsv_to_argb((idle - ATTRACT_IDLE).as_secs_f32() * 12.0, 0.6, 0.25)
+        } else {
+            background
+        };
+
+        // the sound timer doesn't count down while paused, so the beep is silenced until the game resumes
+        let beeping = ch8.is_beeping() && !paused;
+        if let Some(beep) = beep.as_ref().filter(|beep| beeping != (beep.status() == AudioStatus::Playing)) {
+            if beeping { beep.resume() } else { beep.pause() }
+        }
+
+        // visual substitutes for the beep, an inverted display or a border, both follow the audio including its silence while paused
+        let last_beeping = (renderer.beeping, renderer.beep_indicator);
+        renderer.beeping = is_visual_beep && beeping;
+        renderer.beep_indicator = is_beep_indicator && beeping;
 
-            // todo(safe): figure out what texture::update() _actually_ does
-            unsafe { 
-                let op_raw = output_texture.raw();
-                let rawc = r.as_ptr();
-                SDL_UpdateTexture(op_raw, null(), rawc as *const c_void, 64 * 4); 
+        renderer.resize(ch8.display_dimensions());
+
+        if ch8.present(&mut renderer) {
+            if let Some(gif) = gif.as_mut() {
+                gif.present(ch8.display());
+            }
+
+            if !rewinding {
+                rewind_buffer.push(ch8.snapshot());
             }
+        } else if renderer.background != last_background || (renderer.beeping, renderer.beep_indicator) != last_beeping {
+            renderer.draw()?;
+        }
 
-            canvas.clear();
-            canvas.copy(&output_texture, None, None).unwrap();
-            canvas.present();
+        if let Some(video) = raw_video.as_mut() {
+            video.record(&renderer.frame, renderer.width, renderer.height)?;
         }
 
-        canvas.window_mut().set_title(std::format!("ch8-rs - running {} | fps: {}", filename, tr.tick()).as_str())?;
+        if ch8.is_halted() {
+            renderer.canvas.window_mut().set_title(std::format!("ch8-rs - halted {} at {:#05X}", filename, ch8.get_pc()).as_str())?;
+        } else if paused {
+            renderer.canvas.window_mut().set_title(std::format!("ch8-rs - PAUSED {} at {:#05X}", filename, ch8.get_pc()).as_str())?;
+        } else {
+            let fps = tr.tick();
+            renderer.canvas.window_mut().set_title(std::format!("ch8-rs - running {} | fps: {}", filename, fps).as_str())?;
+
+            if let Some(log) = fps_log.as_mut() {
+                log.record(fps)?;
+            }
+        }
         advance = false;
-        ::std::thread::sleep(Duration::from_micros(1500));
+        step_over = false;
+
+        // after a stall (a blocking prompt, a dragged window) start pacing afresh instead of racing to catch up
+        next_frame += frame_period;
+        let now = Instant::now();
+        if next_frame > now {
+            ::std::thread::sleep(next_frame - now);
+        } else if now - next_frame > frame_period {
+            next_frame = now;
+        }
+    }
+
+    if let Some(path) = rpl_file {
+        ch8.save_rpl_flags(Path::new(path))?;
+    }
+
+    if let (Some(gif), Some(path)) = (gif, gif_path) {
+        gif.save(path)?;
+        eprintln!("saved {} frames to {}", gif.frame_count(), path);
     }
 
     Ok(())
 }
+
+// runs the rom without sdl, printing the display to the terminal at 60hz until it halts, there is no keyboard input
+fn run_terminal(ch8: &mut Chip8, mut gif: Option<&mut GifRecorder>) -> Result<()> {
+    let mut renderer = TerminalRenderer { out: io::stdout() };
+    let frame_period = Duration::from_secs(1) / TIMER_RATE;
+    let mut next_frame = Instant::now();
+
+    // clear once, every frame after that only moves the cursor home
+    print!("\x1b[2J");
+
+    while !ch8.is_halted() {
+        if let Err(e) = ch8.run_frame() {
+            println!("{}, halting", e);
+        }
+
+        if ch8.present(&mut renderer) {
+            if let Some(gif) = gif.as_mut() {
+                gif.present(ch8.display());
+            }
+        }
+
+        next_frame += frame_period;
+        let now = Instant::now();
+        if next_frame > now {
+            ::std::thread::sleep(next_frame - now);
+        } else if now - next_frame > frame_period {
+            next_frame = now;
+        }
+    }
+
+    println!("halted at {:#05X}", ch8.get_pc());
+
+    Ok(())
+}
+
+// draws the emulated display into the window through a streaming texture, with the overlays picked on the command line
+struct SdlRenderer<'a> {
+    canvas: Canvas<Window>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    texture: Texture<'a>,
+    width: usize,
+    height: usize,
+    ghost_trail: GhostTrail,
+    ghost_frames: usize,
+    smart_draw: Option<SmartDraw>,
+    mirror: Mirror,
+    scanlines: u8,
+    draw_grid: bool,
+    foreground: u32,
+    background: u32,
+    beeping: bool,
+    beep_indicator: bool, // draw a border around the window
+    display: Vec<u32>, // last display handed over by the emulator
+    frame: Vec<u32> // last frame uploaded to the texture
+}
+
+impl<'a> SdlRenderer<'a> {
+    // the streaming texture has a fixed size, recreate it (and drop trails of the old size) when the resolution changes
+    fn resize(&mut self, (width, height): (usize, usize)) {
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+
+        self.width = width;
+        self.height = height;
+        self.texture = self.texture_creator.create_texture_streaming(Some(PixelFormatEnum::ARGB8888), width as u32, height as u32).unwrap();
+        self.ghost_trail = GhostTrail::new(self.ghost_frames);
+        self.display = vec![0; width * height];
+    }
+
+    // redraws the window from the last display, also used when only the background or the beep inversion changed
+    fn draw(&mut self) -> Result<()> {
+        let (width, height) = (self.width, self.height);
+        let display = match self.smart_draw.as_mut() {
+            Some(smart_draw) => smart_draw.frame(&self.display),
+            None => self.display.clone()
+        };
+
+        let mut r: Vec<u32> = vec![0; width * height];
+        render_argb(&display, &[self.background, self.foreground, PLANE_2, PLANES_BOTH], &mut r);
+
+        for ii in 0..width * height {
+            if display[ii] == 0 {
+                r[ii] = self.ghost_trail.color(ii, self.background);
+            }
+
+            if self.beeping {
+                r[ii] ^= 0x00FFFFFF;
+            }
+        }
+
+        self.mirror.apply(&mut r, width, height);
+
+        // ARGB8888 is a packed format, so each pixel is its u32 in native byte order
+        // the pitch is the length of one texture row in bytes, 4 bytes for every pixel of the row
+        let bytes: Vec<u8> = r.iter().flat_map(|pixel| pixel.to_ne_bytes()).collect();
+        self.texture.update(None, &bytes, width * 4)?;
+        self.frame = r;
+
+        self.canvas.clear();
+        self.canvas.copy(&self.texture, None, None).unwrap();
+
+        if self.scanlines > 0 {
+            draw_scanlines(&mut self.canvas, self.scanlines)?;
+        }
+
+        if self.draw_grid {
+            draw_grid(&mut self.canvas, width, height)?;
+        }
+
+        if self.beep_indicator {
+            draw_beep_border(&mut self.canvas)?;
+        }
+
+        self.canvas.present();
+
+        Ok(())
+    }
+}
+
+impl<'a> Renderer for SdlRenderer<'a> {
+    fn present(&mut self, display: &[u32]) {
+        self.display.copy_from_slice(display);
+
+        if let Err(e) = self.draw() {
+            eprintln!("{:#}", e);
+        }
+
+        self.ghost_trail.push(self.display.clone());
+    }
+}
+
+// prompts for an address and a new byte on stdin and patches memory, the emulator is blocked until a line is entered
+fn edit_memory(ch8: &mut Chip8) {
+    eprint!("edit memory <address> <byte> (hex): ");
+    io::stderr().flush().ok();
+
+    let mut line = String::new();
+    if io::stdin().read_line(&mut line).is_err() {
+        return;
+    }
+
+    let values: Vec<Option<usize>> = line.split_whitespace()
+        .map(|v| usize::from_str_radix(v.trim_start_matches("0x"), 16).ok())
+        .collect();
+
+    match values.as_slice() {
+        [Some(address), Some(value)] if *address < ch8.memory_size() && *value <= 0xFF => {
+            let before = ch8.get_memory(*address);
+            ch8.set_memory(*address, *value as u8);
+            eprintln!("{:#05X}: {:#04X} -> {:#04X}", address, before, value);
+        }
+        _ => eprintln!("invalid input, expected an address below {:#05X} and a byte", ch8.memory_size())
+    }
+}
+
+// darkens every other row of the window to mimic a crt, only touches the canvas, never the emulated display
+fn draw_scanlines(canvas: &mut Canvas<Window>, intensity: u8) -> Result<()> {
+    let (width, height) = canvas.output_size().map_err(anyhow::Error::msg)?;
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, scanline_alpha(intensity)));
+
+    for y in scanline_rows(height) {
+        canvas.draw_line((0, y), (width as i32, y)).map_err(anyhow::Error::msg)?;
+    }
+
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 255));
+    canvas.set_blend_mode(BlendMode::None);
+
+    Ok(())
+}
+
+// amber frame along the window edges, as wide as half an emulated lo-res pixel
+fn draw_beep_border(canvas: &mut Canvas<Window>) -> Result<()> {
+    let (width, height) = canvas.output_size().map_err(anyhow::Error::msg)?;
+    let thickness = (width / VIDEO_WIDTH as u32 / 2).max(1);
+
+    canvas.set_draw_color(Color::RGB(255, 176, 0));
+    canvas.fill_rects(&[
+        Rect::new(0, 0, width, thickness),
+        Rect::new(0, (height - thickness) as i32, width, thickness),
+        Rect::new(0, 0, thickness, height),
+        Rect::new((width - thickness) as i32, 0, thickness, height)
+    ]).map_err(anyhow::Error::msg)?;
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 255));
+
+    Ok(())
+}
+
+// faint lines on the boundaries of the emulated pixels, computed from the window size so they line up at any scale
+fn draw_grid(canvas: &mut Canvas<Window>, columns: usize, rows: usize) -> Result<()> {
+    let (width, height) = canvas.output_size().map_err(anyhow::Error::msg)?;
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(255, 255, 255, 40));
+
+    for column in 1..columns as u32 {
+        let x = (column * width / columns as u32) as i32;
+        canvas.draw_line((x, 0), (x, height as i32)).map_err(anyhow::Error::msg)?;
+    }
+
+    for row in 1..rows as u32 {
+        let y = (row * height / rows as u32) as i32;
+        canvas.draw_line((0, y), (width as i32, y)).map_err(anyhow::Error::msg)?;
+    }
+
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 255));
+    canvas.set_blend_mode(BlendMode::None);
+
+    Ok(())
+}
@@ -0,0 +1,191 @@
+// decoded chip8 instruction, variants are named after the instruction handlers on Chip8
+// x/y operands are register numbers, nn is a byte value, nnn an address and n a sprite height
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Opcode {
+    Cls,                   // 00E0
+    Ret,                   // 00EE
+    ScrollDown(u8),        // 00Cn
+    ScrollRight,           // 00FB
+    ScrollLeft,            // 00FC
+    LoRes,                 // 00FE
+    HiRes,                 // 00FF
+    Sys(u16),              // 0nnn
+    Jmp(u16),              // 1nnn
+    Call(u16),             // 2nnn
+    SeVal(u8, u8),         // 3xnn
+    SneVal(u8, u8),        // 4xnn
+    SeReg(u8, u8),         // 5xy0
+    LdReg(u8, u8),         // 6xnn
+    AddVal(u8, u8),        // 7xnn
+    Copy(u8, u8),          // 8xy0
+    Or(u8, u8),            // 8xy1
+    And(u8, u8),           // 8xy2
+    Xor(u8, u8),           // 8xy3
+    AddReg(u8, u8),        // 8xy4
+    SubRegXY(u8, u8),      // 8xy5
+    ShiftR(u8, u8),        // 8xy6
+    SubRegYX(u8, u8),      // 8xy7
+    ShiftL(u8, u8),        // 8xyE
+    SneReg(u8, u8),        // 9xy0
+    LdIndx(u16),           // Annn
+    JmpAdd(u16),           // Bnnn
+    RandAnd(u8, u8),       // Cxnn
+    DrawPixel(u8, u8, u8), // Dxyn
+    SeKp(u8),              // Ex9E
+    SneKp(u8),             // ExA1
+    GetDelay(u8),          // Fx07
+    WaitKey(u8),           // Fx0A
+    SetDelay(u8),          // Fx15
+    SetSound(u8),          // Fx18
+    AddIndx(u8),           // Fx1E
+    GetFontChr(u8),        // Fx29
+    EncodeSave(u8),        // Fx33
+    Save(u8),              // Fx55
+    Load(u8),              // Fx65
+    Plane(u8),             // Fn01
+    SaveFlags(u8),         // Fx75
+    LoadFlags(u8),         // Fx85
+    Unknown(u16)
+}
+
+// an executed instruction, see Chip8::cycle_one
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedInstr {
+    pub pc: u16, // address the instruction was fetched from
+    pub opcode: u16,
+    pub instr: Opcode, // the operands live in the variant
+    pub mnemonic: String // in disassembler syntax, e.g. LD V0, 0x05
+}
+
+pub fn decode(opcode: u16) -> Opcode {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let nn = (opcode & 0x00FF) as u8;
+    let nnn = opcode & 0x0FFF;
+
+    match (opcode & 0xF000) >> 12 {
+        0x0 => match opcode {
+            0x00E0 => Opcode::Cls,
+            0x00EE => Opcode::Ret,
+            0x00FB => Opcode::ScrollRight,
+            0x00FC => Opcode::ScrollLeft,
+            0x00FE => Opcode::LoRes,
+            0x00FF => Opcode::HiRes,
+            _ if opcode & 0xFFF0 == 0x00C0 => Opcode::ScrollDown(n),
+            _ => Opcode::Sys(nnn)
+        },
+        0x1 => Opcode::Jmp(nnn),
+        0x2 => Opcode::Call(nnn),
+        0x3 => Opcode::SeVal(x, nn),
+        0x4 => Opcode::SneVal(x, nn),
+        0x5 if n == 0 => Opcode::SeReg(x, y),
+        0x6 => Opcode::LdReg(x, nn),
+        0x7 => Opcode::AddVal(x, nn),
+        0x8 => match n {
+            0x0 => Opcode::Copy(x, y),
+            0x1 => Opcode::Or(x, y),
+            0x2 => Opcode::And(x, y),
+            0x3 => Opcode::Xor(x, y),
+            0x4 => Opcode::AddReg(x, y),
+            0x5 => Opcode::SubRegXY(x, y),
+            0x6 => Opcode::ShiftR(x, y),
+            0x7 => Opcode::SubRegYX(x, y),
+            0xE => Opcode::ShiftL(x, y),
+            _ => Opcode::Unknown(opcode)
+        },
+        0x9 if n == 0 => Opcode::SneReg(x, y),
+        0xA => Opcode::LdIndx(nnn),
+        0xB => Opcode::JmpAdd(nnn),
+        0xC => Opcode::RandAnd(x, nn),
+        0xD => Opcode::DrawPixel(x, y, n),
+        0xE => match nn {
+            0x9E => Opcode::SeKp(x),
+            0xA1 => Opcode::SneKp(x),
+            _ => Opcode::Unknown(opcode)
+        },
+        0xF => match nn {
+            0x01 => Opcode::Plane(x),
+            0x07 => Opcode::GetDelay(x),
+            0x0A => Opcode::WaitKey(x),
+            0x15 => Opcode::SetDelay(x),
+            0x18 => Opcode::SetSound(x),
+            0x1E => Opcode::AddIndx(x),
+            0x29 => Opcode::GetFontChr(x),
+            0x33 => Opcode::EncodeSave(x),
+            0x55 => Opcode::Save(x),
+            0x65 => Opcode::Load(x),
+            0x75 => Opcode::SaveFlags(x),
+            0x85 => Opcode::LoadFlags(x),
+            _ => Opcode::Unknown(opcode)
+        },
+        _ => Opcode::Unknown(opcode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one opcode per variant, with distinct operands so swapped fields show up
+    #[test]
+    fn decodes_every_variant() {
+        let table = [
+            (0x00E0, Opcode::Cls),
+            (0x00EE, Opcode::Ret),
+            (0x00C7, Opcode::ScrollDown(7)),
+            (0x00FB, Opcode::ScrollRight),
+            (0x00FC, Opcode::ScrollLeft),
+            (0x00FE, Opcode::LoRes),
+            (0x00FF, Opcode::HiRes),
+            (0x0123, Opcode::Sys(0x123)),
+            (0x1ABC, Opcode::Jmp(0xABC)),
+            (0x2ABC, Opcode::Call(0xABC)),
+            (0x3A42, Opcode::SeVal(0xA, 0x42)),
+            (0x4A42, Opcode::SneVal(0xA, 0x42)),
+            (0x5AB0, Opcode::SeReg(0xA, 0xB)),
+            (0x6A42, Opcode::LdReg(0xA, 0x42)),
+            (0x7A42, Opcode::AddVal(0xA, 0x42)),
+            (0x8AB0, Opcode::Copy(0xA, 0xB)),
+            (0x8AB1, Opcode::Or(0xA, 0xB)),
+            (0x8AB2, Opcode::And(0xA, 0xB)),
+            (0x8AB3, Opcode::Xor(0xA, 0xB)),
+            (0x8AB4, Opcode::AddReg(0xA, 0xB)),
+            (0x8AB5, Opcode::SubRegXY(0xA, 0xB)),
+            (0x8AB6, Opcode::ShiftR(0xA, 0xB)),
+            (0x8AB7, Opcode::SubRegYX(0xA, 0xB)),
+            (0x8ABE, Opcode::ShiftL(0xA, 0xB)),
+            (0x9AB0, Opcode::SneReg(0xA, 0xB)),
+            (0xAABC, Opcode::LdIndx(0xABC)),
+            (0xBABC, Opcode::JmpAdd(0xABC)),
+            (0xCA42, Opcode::RandAnd(0xA, 0x42)),
+            (0xDAB5, Opcode::DrawPixel(0xA, 0xB, 5)),
+            (0xEA9E, Opcode::SeKp(0xA)),
+            (0xEAA1, Opcode::SneKp(0xA)),
+            (0xFA07, Opcode::GetDelay(0xA)),
+            (0xFA0A, Opcode::WaitKey(0xA)),
+            (0xFA15, Opcode::SetDelay(0xA)),
+            (0xFA18, Opcode::SetSound(0xA)),
+            (0xFA1E, Opcode::AddIndx(0xA)),
+            (0xFA29, Opcode::GetFontChr(0xA)),
+            (0xFA33, Opcode::EncodeSave(0xA)),
+            (0xFA55, Opcode::Save(0xA)),
+            (0xFA65, Opcode::Load(0xA)),
+            (0xF201, Opcode::Plane(2)),
+            (0xFA75, Opcode::SaveFlags(0xA)),
+            (0xFA85, Opcode::LoadFlags(0xA))
+        ];
+
+        for (opcode, expected) in &table {
+            assert_eq!(decode(*opcode), *expected, "{:04X}", opcode);
+        }
+    }
+
+    // sub-opcodes that don't exist decode as Unknown with the whole word kept
+    #[test]
+    fn decodes_unknown_sub_opcodes() {
+        for opcode in &[0x5AB1, 0x8AB8, 0x8ABF, 0x9AB1, 0xEA00, 0xFAFF] {
+            assert_eq!(decode(*opcode), Opcode::Unknown(*opcode), "{:04X}", opcode);
+        }
+    }
+}
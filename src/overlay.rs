@@ -0,0 +1,191 @@
+use anyhow::{Error, bail};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+// flicker reduction, pixels stay lit for one more presented frame after they were erased so the erase half of an
+// erase/redraw pair never shows up on its own, only changes what is presented, not the emulated display or VF
+pub struct SmartDraw {
+    previous: Vec<u32>,
+    presented: Vec<u32>
+}
+
+impl SmartDraw {
+    pub fn new() -> Self {
+        Self { previous: Vec::new(), presented: Vec::new() }
+    }
+
+    // an unchanged display presents the same frame again, so redraws for other reasons don't drop held pixels
+    pub fn frame(&mut self, display: &[u32]) -> Vec<u32> {
+        if self.previous.as_slice() == display {
+            return self.presented.clone();
+        }
+
+        self.presented = if self.previous.len() == display.len() {
+            display.iter().zip(self.previous.iter()).map(|(current, previous)| current | previous).collect()
+        } else {
+            display.to_vec()
+        };
+        self.previous = display.to_vec();
+
+        self.presented.clone()
+    }
+}
+
+// keeps the last few presented frames around so pixels that were lit recently can be drawn as a fading trail
+pub struct GhostTrail {
+    frames: VecDeque<Vec<u32>>, // most recent frame first
+    length: usize
+}
+
+impl GhostTrail {
+    pub fn new(length: usize) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(length + 1),
+            length
+        }
+    }
+
+    pub fn push(&mut self, frame: Vec<u32>) {
+        if self.length == 0 {
+            return;
+        }
+
+        self.frames.push_front(frame);
+        self.frames.truncate(self.length);
+    }
+
+    // color for an unlit pixel, the more recently it was lit the brighter the trail
+    pub fn color(&self, index: usize, background: u32) -> u32 {
+        for (age, frame) in self.frames.iter().enumerate() {
+            if frame[index] != 0 {
+                let level = (0x60 * (self.length - age) / self.length) as u32;
+                return 0xFF000000 | (level << 16) | (level << 8) | level;
+            }
+        }
+
+        background
+    }
+}
+
+// argb color for a hue in degrees, saturation and value range from 0.0 to 1.0
+pub fn hsv_to_argb(hue: f32, saturation: f32, value: f32) -> u32 {
+    let chroma = value * saturation;
+    let sector = (hue % 360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x)
+    };
+
+    let m = value - chroma;
+    let channel = |v: f32| ((v + m) * 255.0).round() as u32;
+
+    0xFF000000 | (channel(r) << 16) | (channel(g) << 8) | channel(b)
+}
+
+// crt style scanlines, a translucent black line over every other row of the window
+// they are drawn on the canvas after the frame, so the emulated display and its hash never see them
+pub fn scanline_rows(window_height: u32) -> impl Iterator<Item = i32> {
+    (0..window_height as i32).step_by(2)
+}
+
+// opacity of the scanlines, intensity ranges from 0 to 100
+pub fn scanline_alpha(intensity: u8) -> u8 {
+    (intensity as u32 * 255 / 100) as u8
+}
+
+// flips the pixel buffer before it is uploaded, for roms that expect a mirrored display
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mirror {
+    None,
+    Horizontal,
+    Vertical,
+    Both
+}
+
+impl FromStr for Mirror {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Mirror::None),
+            "horizontal" => Ok(Mirror::Horizontal),
+            "vertical" => Ok(Mirror::Vertical),
+            "both" => Ok(Mirror::Both),
+            _ => bail!("unknown mirror mode '{}', expected horizontal, vertical or both", s)
+        }
+    }
+}
+
+impl Mirror {
+    pub fn apply(&self, buffer: &mut [u32], width: usize, height: usize) {
+        if *self == Mirror::Horizontal || *self == Mirror::Both {
+            for row in buffer.chunks_mut(width) {
+                row.reverse();
+            }
+        }
+
+        if *self == Mirror::Vertical || *self == Mirror::Both {
+            for row in 0..height / 2 {
+                for column in 0..width {
+                    buffer.swap(row * width + column, (height - 1 - row) * width + column);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ch8_rs::Chip8;
+
+    #[test]
+    fn darkens_every_other_row() {
+        assert_eq!(scanline_rows(7).collect::<Vec<_>>(), [0, 2, 4, 6]);
+        assert_eq!(scanline_alpha(0), 0);
+        assert_eq!(scanline_alpha(100), 255);
+    }
+
+    // draws the 0 glyph and shows it in a window scaled 4 times, blending in the scanlines like the canvas does
+    fn run_with_scanlines(intensity: u8) -> (Vec<u32>, u64, Vec<u32>) {
+        let mut ch8 = Chip8::default();
+        ch8.load_rom_bytes(&[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x06]).unwrap();
+        ch8.run_cycles(4).unwrap();
+
+        let (width, height) = ch8.display_dimensions();
+        let mut window: Vec<u32> = (0..width * 4 * height * 4).map(|ii| {
+            let (x, y) = (ii % (width * 4) / 4, ii / (width * 4) / 4);
+            if ch8.display()[y * width + x] != 0 { 0xFFFFFFFF } else { 0xFF000000 }
+        }).collect();
+
+        if intensity > 0 {
+            let keep = 255 - scanline_alpha(intensity) as u32;
+            for y in scanline_rows((height * 4) as u32) {
+                for pixel in window[y as usize * width * 4..(y as usize + 1) * width * 4].iter_mut() {
+                    let channel = |shift: u32| ((*pixel >> shift & 0xFF) * keep / 255) << shift;
+                    *pixel = 0xFF000000 | channel(16) | channel(8) | channel(0);
+                }
+            }
+        }
+
+        (ch8.display().to_vec(), ch8.display_hash(), window)
+    }
+
+    // the scanlines only change what the window shows, the machine behind it ends up with the same display
+    #[test]
+    fn scanlines_leave_display_alone() {
+        let (display_off, hash_off, window_off) = run_with_scanlines(0);
+        let (display_on, hash_on, window_on) = run_with_scanlines(50);
+
+        assert_eq!(display_on, display_off);
+        assert_eq!(hash_on, hash_off);
+        assert_ne!(window_on, window_off);
+        assert!(display_off.iter().any(|&pixel| pixel != 0));
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::Result;
+
+use ch8_rs::ch8::{Chip8, QUIRK_FLAGS, Quirks};
+use ch8_rs::error::Ch8Error;
+
+static FUZZ_CYCLES: usize = 200_000; // cycles each quirk combination is run for
+
+// Chip8::display_hash after running the rom headless with the given quirks, stable across builds so --expect-hash values keep matching
+fn display_hash(rom: &[u8], quirks: Quirks) -> Result<u64> {
+    let mut ch8 = Chip8::default();
+    ch8.set_quirks(quirks);
+    ch8.set_seed(0);
+    ch8.load_rom_bytes(rom)?;
+
+    let _ = ch8.run_cycles(FUZZ_CYCLES);
+
+    Ok(ch8.display_hash())
+}
+
+// every combination of the boolean quirks grouped by the display hash it ends on, in order of first appearance
+// Cxnn is seeded the same for every run, so random numbers don't make combinations look different
+fn group_outputs(rom: &[u8]) -> Result<Vec<(u64, Vec<String>)>> {
+    let mut outputs: Vec<(u64, Vec<String>)> = Vec::new();
+
+    for combination in 0..1usize << QUIRK_FLAGS.len() {
+        let mut quirks = Quirks::default();
+        let mut enabled = Vec::new();
+
+        for (ii, (name, flag)) in QUIRK_FLAGS.iter().enumerate() {
+            if combination & (1 << ii) != 0 {
+                *flag(&mut quirks) = true;
+                enabled.push(*name);
+            }
+        }
+
+        let hash = display_hash(rom, quirks)?;
+        let label = if enabled.is_empty() { String::from("(none)") } else { enabled.join(",") };
+        println!("{:016x} {}", hash, label);
+
+        match outputs.iter_mut().find(|(h, _)| *h == hash) {
+            Some((_, combinations)) => combinations.push(label),
+            None => outputs.push((hash, vec![label]))
+        }
+    }
+
+    Ok(outputs)
+}
+
+// the combinations that produced the known-good hash
+fn matching(outputs: &[(u64, Vec<String>)], expected: u64) -> Option<&[String]> {
+    outputs.iter().find(|(h, _)| *h == expected).map(|(_, combinations)| combinations.as_slice())
+}
+
+// runs the rom under every combination of the boolean quirks and reports which combinations produce distinct displays
+pub fn quirk_fuzzer(rom: &str, expected_hash: Option<u64>) -> Result<()> {
+    let bytes = std::fs::read(rom).map_err(|_| Ch8Error::RomNotFound(String::from(rom)))?;
+    let outputs = group_outputs(&bytes)?;
+
+    println!("{} distinct outputs from {} combinations", outputs.len(), 1usize << QUIRK_FLAGS.len());
+
+    if let Some(expected) = expected_hash {
+        match matching(&outputs, expected) {
+            Some(combinations) => println!("matching {:016x}: {}", expected, combinations.join(" | ")),
+            None => println!("no combination matches {:016x}", expected)
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // shifts 0x10 in V0 with 0x02 in V1 and draws V0, a 1 when the shift came out of V1 (cosmac vip) and an 8 otherwise
+    static SHIFT_ROM: &[u8] = &[0x60, 0x10, 0x61, 0x02, 0x80, 0x16, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x0A];
+
+    #[test]
+    fn splits_shift_modes() {
+        let outputs = group_outputs(SHIFT_ROM).unwrap();
+        assert_eq!(outputs.len(), 2);
+
+        for (_, combinations) in &outputs {
+            let shifting_vy = combinations.iter().filter(|label| label.contains("shift_uses_vy")).count();
+            assert!(shifting_vy == 0 || shifting_vy == combinations.len());
+        }
+    }
+
+    #[test]
+    fn matches_known_good_hash() {
+        let outputs = group_outputs(SHIFT_ROM).unwrap();
+        let known_good = display_hash(SHIFT_ROM, Quirks { shift_uses_vy: true, ..Quirks::default() }).unwrap();
+
+        let combinations = matching(&outputs, known_good).unwrap();
+        assert_eq!(combinations.len(), 1 << (QUIRK_FLAGS.len() - 1));
+        assert!(combinations.iter().all(|label| label.contains("shift_uses_vy")));
+
+        assert_eq!(matching(&outputs, 0), None);
+    }
+}
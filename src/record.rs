@@ -0,0 +1,200 @@
+use anyhow::{Context, Result};
+use gif::{Encoder, Frame, Repeat};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+
+use ch8_rs::ch8::{HIRES_VIDEO_HEIGHT, HIRES_VIDEO_WIDTH, VIDEO_HEIGHT, VIDEO_WIDTH};
+use ch8_rs::render::{BACKGROUND, FOREGROUND, PLANE_2, PLANES_BOTH, Renderer};
+
+pub static GIF_FRAME_INTERVAL: Duration = Duration::from_millis(50); // frames presented faster than 20 fps are merged
+pub static GIF_MAX_FRAMES: usize = 3000; // 2.5 minutes at 20 fps, 8k of memory per frame
+
+// writes the presented frames to stdout as raw rgb24 at a fixed rate, frames are repeated when nothing new was drawn
+// lo-res frames are doubled up to the hi-res size so a rom switching resolution keeps a single video size
+// e.g. ch8-rs game.ch8 --record-video-raw | ffmpeg -f rawvideo -pix_fmt rgb24 -s 128x64 -r 60 -i - out.mp4
+pub struct RawVideo {
+    stdout: Stdout,
+    interval: Duration,
+    next_frame: Instant,
+    started: bool
+}
+
+impl RawVideo {
+    pub fn new(rate: u32) -> Self {
+        Self {
+            stdout: io::stdout(),
+            interval: Duration::from_secs(1) / rate,
+            next_frame: Instant::now(),
+            started: false
+        }
+    }
+
+    // writes the argb frame as often as the rate requires since the last call, every frame is 128 * 64 * 3 bytes
+    pub fn record(&mut self, frame: &[u32], width: usize, height: usize) -> Result<()> {
+        if !self.started {
+            self.started = true;
+            eprintln!("recording raw rgb24 video, {}x{} at {} fps", HIRES_VIDEO_WIDTH, HIRES_VIDEO_HEIGHT, Duration::from_secs(1).as_nanos() / self.interval.as_nanos());
+        }
+
+        let bytes = raw_frame(frame, width, height);
+
+        let mut out = self.stdout.lock();
+        while Instant::now() >= self.next_frame {
+            out.write_all(&bytes)?;
+            self.next_frame += self.interval;
+        }
+        out.flush()?;
+
+        Ok(())
+    }
+}
+
+// the argb frame scaled up to the hi-res size as rgb24 bytes
+fn raw_frame(frame: &[u32], width: usize, height: usize) -> Vec<u8> {
+    let (scale_x, scale_y) = (HIRES_VIDEO_WIDTH / width, HIRES_VIDEO_HEIGHT / height);
+
+    (0..HIRES_VIDEO_WIDTH * HIRES_VIDEO_HEIGHT)
+        .map(|i| frame[(i / HIRES_VIDEO_WIDTH / scale_y) * width + (i % HIRES_VIDEO_WIDTH) / scale_x])
+        .flat_map(|argb| vec![(argb >> 16) as u8, (argb >> 8) as u8, argb as u8])
+        .collect()
+}
+
+// keeps the presented frames in memory and writes them as an animated gif, see --record
+// every frame is stored at the hi-res size so a rom switching resolution keeps a single gif size
+pub struct GifRecorder {
+    frames: Vec<(Vec<u8>, Instant)>, // palette indices and when the frame was presented
+    interval: Duration,
+    max_frames: usize,
+    full: bool
+}
+
+impl GifRecorder {
+    pub fn new(interval: Duration, max_frames: usize) -> Self {
+        Self {
+            frames: Vec::new(),
+            interval,
+            max_frames,
+            full: false
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    // encodes the recording, every frame is shown until the next one was presented
+    pub fn encode<W: Write>(&self, out: W) -> Result<W> {
+        let palette: Vec<u8> = [BACKGROUND, FOREGROUND, PLANE_2, PLANES_BOTH].iter()
+            .flat_map(|argb| vec![(argb >> 16) as u8, (argb >> 8) as u8, *argb as u8])
+            .collect();
+
+        let mut encoder = Encoder::new(out, HIRES_VIDEO_WIDTH as u16, HIRES_VIDEO_HEIGHT as u16, &palette)?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        for (i, (pixels, presented)) in self.frames.iter().enumerate() {
+            let shown = match self.frames.get(i + 1) {
+                Some((_, next)) => *next - *presented,
+                None => self.interval
+            };
+
+            let frame = Frame {
+                width: HIRES_VIDEO_WIDTH as u16,
+                height: HIRES_VIDEO_HEIGHT as u16,
+                delay: (shown.as_millis() / 10).clamp(1, u16::MAX as u128) as u16, // hundredths of a second
+                buffer: Cow::Borrowed(pixels),
+                ..Frame::default()
+            };
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(encoder.into_inner()?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let file = File::create(path).with_context(|| std::format!("failed to create {}", path))?;
+        self.encode(file).with_context(|| std::format!("failed to write gif {}", path))?;
+
+        Ok(())
+    }
+}
+
+impl Renderer for GifRecorder {
+    fn present(&mut self, display: &[u32]) {
+        // lo-res pixels are doubled up to the hi-res size
+        let scale = if display.len() == VIDEO_WIDTH * VIDEO_HEIGHT { 2 } else { 1 };
+        let width = HIRES_VIDEO_WIDTH / scale;
+        let pixels: Vec<u8> = (0..HIRES_VIDEO_WIDTH * HIRES_VIDEO_HEIGHT)
+            .map(|i| (display[(i / HIRES_VIDEO_WIDTH / scale) * width + (i % HIRES_VIDEO_WIDTH) / scale] & 0x3) as u8)
+            .collect();
+
+        // a frame shortly after the last one replaces it, the recording keeps the latest picture at the capped rate
+        if let Some((last, presented)) = self.frames.last_mut() {
+            if presented.elapsed() < self.interval {
+                *last = pixels;
+                return;
+            }
+        }
+
+        if self.frames.len() < self.max_frames {
+            self.frames.push((pixels, Instant::now()));
+        } else if !self.full {
+            self.full = true;
+            eprintln!("gif recording is full after {} frames, later frames are dropped", self.max_frames);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoded_frames(recorder: &GifRecorder) -> usize {
+        let gif = recorder.encode(Vec::new()).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(gif.as_slice()).unwrap();
+
+        let mut count = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            count += 1;
+        }
+
+        count
+    }
+
+    // a lo-res frame comes out at the hi-res size, each pixel doubled in both directions
+    #[test]
+    fn scales_raw_video_frames() {
+        let mut frame = vec![0xFF000000; VIDEO_WIDTH * VIDEO_HEIGHT];
+        frame[VIDEO_WIDTH + 1] = 0xFF123456;
+
+        let bytes = raw_frame(&frame, VIDEO_WIDTH, VIDEO_HEIGHT);
+        let pixel = |x: usize, y: usize| bytes[(y * HIRES_VIDEO_WIDTH + x) * 3..(y * HIRES_VIDEO_WIDTH + x) * 3 + 3].to_vec();
+
+        assert_eq!(bytes.len(), HIRES_VIDEO_WIDTH * HIRES_VIDEO_HEIGHT * 3);
+        for (x, y) in &[(2, 2), (3, 2), (2, 3), (3, 3)] {
+            assert_eq!(pixel(*x, *y), [0x12, 0x34, 0x56]);
+        }
+        assert_eq!(pixel(1, 1), [0, 0, 0]);
+        assert_eq!(pixel(4, 2), [0, 0, 0]);
+
+        let hires = vec![0xFFABCDEF; HIRES_VIDEO_WIDTH * HIRES_VIDEO_HEIGHT];
+        assert_eq!(raw_frame(&hires, HIRES_VIDEO_WIDTH, HIRES_VIDEO_HEIGHT), [0xAB, 0xCD, 0xEF].repeat(HIRES_VIDEO_WIDTH * HIRES_VIDEO_HEIGHT));
+    }
+
+    // three different frames, one lo-res and two hi-res, encode to a three frame gif, a cap of two keeps the first two
+    #[test]
+    fn records_gif_frames() {
+        let frames = [vec![1; VIDEO_WIDTH * VIDEO_HEIGHT], vec![0; HIRES_VIDEO_WIDTH * HIRES_VIDEO_HEIGHT], vec![3; HIRES_VIDEO_WIDTH * HIRES_VIDEO_HEIGHT]];
+        let mut all = GifRecorder::new(Duration::from_secs(0), 10);
+        let mut capped = GifRecorder::new(Duration::from_secs(0), 2);
+
+        for frame in &frames {
+            all.present(frame);
+            capped.present(frame);
+        }
+
+        assert_eq!(decoded_frames(&all), 3);
+        assert_eq!(decoded_frames(&capped), 2);
+    }
+}
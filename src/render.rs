@@ -0,0 +1,125 @@
+use anyhow::{Error, Result, bail};
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::ch8::{HIRES_VIDEO_WIDTH, VIDEO_HEIGHT, VIDEO_WIDTH};
+
+pub static BACKGROUND: u32 = 0xFF000000;
+pub static FOREGROUND: u32 = 0xFFFFFFFF;
+pub static PLANE_2: u32 = 0xFF808080; // xo-chip pixels lit only in plane 2
+pub static PLANES_BOTH: u32 = 0xFFC0C0C0; // xo-chip pixels lit in both planes
+
+// where the display goes, the sdl window or the terminal the emulator was started from
+#[derive(Clone, Copy, PartialEq)]
+pub enum RenderMode {
+    Sdl,
+    Terminal
+}
+
+impl FromStr for RenderMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sdl" => Ok(RenderMode::Sdl),
+            "terminal" => Ok(RenderMode::Terminal),
+            _ => bail!("unknown render mode '{}', expected sdl or terminal", s)
+        }
+    }
+}
+
+// converts the emulated display to argb pixels by looking up every pixel's plane bits in the palette
+// palette[0] is the background, palette[1] plane 1, palette[2] plane 2 and palette[3] pixels lit in both planes
+// out has to be at least as long as display
+pub fn render_argb(display: &[u32], palette: &[u32; 4], out: &mut [u32]) {
+    for (pixel, planes) in out.iter_mut().zip(display.iter()) {
+        *pixel = palette[(*planes & 0x3) as usize];
+    }
+}
+
+// receives the display whenever the emulator has drawn something new, see Chip8::present
+pub trait Renderer {
+    fn present(&mut self, display: &[u32]);
+}
+
+// discards every frame, for running headless
+pub struct NullRenderer;
+
+impl Renderer for NullRenderer {
+    fn present(&mut self, _display: &[u32]) { }
+}
+
+// draws the display as text, one line per row with a full block for every lit pixel in any plane
+pub fn render_terminal(display: &[u32], width: usize) -> String {
+    let mut out = String::with_capacity(display.len() * 3 + display.len() / width);
+
+    for row in display.chunks(width) {
+        out.extend(row.iter().map(|planes| if *planes & 0x3 != 0 { '█' } else { ' ' }));
+        out.push('\n');
+    }
+
+    out
+}
+
+// prints every frame to out, moving the cursor home first so frames overwrite each other instead of scrolling
+pub struct TerminalRenderer<W: Write> {
+    pub out: W
+}
+
+impl<W: Write> Renderer for TerminalRenderer<W> {
+    fn present(&mut self, display: &[u32]) {
+        let width = if display.len() == VIDEO_WIDTH * VIDEO_HEIGHT { VIDEO_WIDTH } else { HIRES_VIDEO_WIDTH };
+
+        // a closed or full terminal only costs frames, the emulator keeps running
+        let _ = write!(self.out, "\x1b[H{}", render_terminal(display, width));
+        let _ = self.out.flush();
+    }
+}
+
+// parses an RRGGBB hex color, optionally prefixed with #, into an opaque argb value
+pub fn parse_rgb(s: &str) -> Result<u32> {
+    let hex = s.trim_start_matches('#');
+
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("invalid color '{}', expected RRGGBB", s);
+    }
+
+    Ok(0xFF000000 | u32::from_str_radix(hex, 16)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_rgb("FFFFFF").unwrap(), 0xFFFFFFFF);
+        assert_eq!(parse_rgb("000000").unwrap(), 0xFF000000);
+        assert_eq!(parse_rgb("#1a2B3c").unwrap(), 0xFF1A2B3C);
+
+        for invalid in &["12345G", "FFF", "+12345"] {
+            assert!(parse_rgb(invalid).is_err(), "{} parsed", invalid);
+        }
+    }
+
+    // any plane lights a pixel, the terminal renderer moves the cursor home before the same text
+    #[test]
+    fn renders_to_terminal() {
+        let mut display = vec![0; VIDEO_WIDTH * VIDEO_HEIGHT];
+        display[0] = 1;
+        display[VIDEO_WIDTH + 1] = 2;
+        display[VIDEO_WIDTH * 2 - 1] = 3;
+
+        let text = render_terminal(&display, VIDEO_WIDTH);
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), VIDEO_HEIGHT);
+        assert_eq!(lines[0], std::format!("█{}", " ".repeat(VIDEO_WIDTH - 1)));
+        assert_eq!(lines[1], std::format!(" █{}█", " ".repeat(VIDEO_WIDTH - 3)));
+        assert!(lines[2..].iter().all(|line| *line == " ".repeat(VIDEO_WIDTH)));
+
+        let mut renderer = TerminalRenderer { out: Vec::new() };
+        renderer.present(&display);
+        assert_eq!(renderer.out, std::format!("\x1b[H{}", text).into_bytes());
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+use crate::ch8::Chip8State;
+
+// nominal amount of drawn frames per second, used to size the buffer from a duration
+static FRAMES_PER_SECOND: usize = 60;
+
+// snapshot with memory and display run length encoded, both are mostly runs of zeros
+// which brings a ~12KB snapshot down to a few hundred bytes for most roms
+struct CompressedState {
+    state: Chip8State, // memory and display are left empty
+    memory: Vec<(u8, u16)>,
+    display: Vec<(u32, u16)>
+}
+
+// ring buffer of recent machine states, the oldest snapshot is dropped once the capacity is reached
+// snapshots are meant to be pushed on drawn frames only, so rewinding steps back one visible change at a time
+// and idle frames don't eat into the history, the trade-off is that rewind granularity follows the rom's draw rate
+pub struct RewindBuffer {
+    snapshots: VecDeque<CompressedState>,
+    capacity: usize
+}
+
+impl RewindBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity
+        }
+    }
+
+    pub fn with_seconds(seconds: usize) -> Self {
+        Self::new(seconds * FRAMES_PER_SECOND)
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn push(&mut self, mut state: Chip8State) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+
+        let memory = rle_encode(&state.memory);
+        let display = rle_encode(&state.display);
+        state.memory = Vec::new();
+        state.display = Vec::new();
+
+        self.snapshots.push_back(CompressedState { state, memory, display });
+    }
+
+    // most recent snapshot, removed from the buffer
+    pub fn pop(&mut self) -> Option<Chip8State> {
+        self.snapshots.pop_back().map(|compressed| {
+            let mut state = compressed.state;
+            state.memory = rle_decode(&compressed.memory);
+            state.display = rle_decode(&compressed.display);
+            state
+        })
+    }
+}
+
+fn rle_encode<T: Copy + PartialEq>(data: &[T]) -> Vec<(T, u16)> {
+    let mut runs: Vec<(T, u16)> = Vec::new();
+
+    for value in data {
+        match runs.last_mut() {
+            Some((last, count)) if *last == *value && *count < u16::MAX => *count += 1,
+            _ => runs.push((*value, 1))
+        }
+    }
+
+    runs
+}
+
+fn rle_decode<T: Copy>(runs: &[(T, u16)]) -> Vec<T> {
+    let mut data = Vec::new();
+
+    for (value, count) in runs {
+        data.extend(std::iter::repeat_n(*value, *count as usize));
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ch8::Chip8;
+
+    // records 100 frames of a counter that is drawn every frame, rewinding 10 of them has to land on the 90th frame
+    #[test]
+    fn rewinds_to_recorded_state() {
+        let mut ch8 = Chip8::new();
+        let mut buffer = RewindBuffer::new(50);
+        let mut recorded = Vec::new();
+
+        // V0 += 1, I = font glyph of V0's low nibble, draw it and loop
+        ch8.load_rom_bytes(&[0x70, 0x01, 0x61, 0x0F, 0x81, 0x02, 0xF1, 0x29, 0xD2, 0x25, 0x12, 0x00]).unwrap();
+
+        for _ in 0..100 {
+            ch8.run_frame().unwrap();
+            recorded.push(ch8.snapshot());
+            buffer.push(ch8.snapshot());
+        }
+
+        let mut state = None;
+        for _ in 0..10 {
+            state = buffer.pop();
+        }
+
+        ch8.restore(&state.unwrap());
+        assert_eq!(buffer.len(), 40);
+        assert!(ch8.snapshot() == recorded[90]);
+    }
+
+    // a full buffer drops its oldest snapshot, so popping everything ends at the first one still kept
+    #[test]
+    fn evicts_oldest_at_capacity() {
+        let mut ch8 = Chip8::new();
+        let mut buffer = RewindBuffer::new(3);
+
+        // V0 += 1, loop
+        ch8.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+        for _ in 0..5 {
+            ch8.run_cycles(2).unwrap();
+            buffer.push(ch8.snapshot());
+        }
+
+        assert_eq!(buffer.len(), 3);
+        let counters: Vec<u8> = std::iter::from_fn(|| buffer.pop()).map(|state| state.registers[0]).collect();
+        assert_eq!(counters, [5, 4, 3]);
+        assert!(buffer.is_empty());
+    }
+
+    // runs longer than a u16 are split, the decoded data matches the input
+    #[test]
+    fn rle_round_trips() {
+        let mut data = vec![0u8; 70000];
+        data[1] = 7;
+        data[2] = 7;
+        data.push(9);
+
+        let runs = rle_encode(&data);
+        assert_eq!(runs[..3], [(0, 1), (7, 2), (0, u16::MAX)]);
+        assert_eq!(rle_decode(&runs), data);
+        assert!(rle_decode::<u32>(&rle_encode(&[])).is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::{Context, Result, bail};
+use std::convert::TryInto;
+
+use crate::ch8::Chip8State;
+
+// save files start with a magic, a version byte and a list of tagged fields: tag (u16), length (u32), value, all big endian
+// loading skips tags it doesn't know and keeps the current value for fields that are missing, so fields can be
+// added without breaking older saves, the version only changes when an existing field changes meaning
+static MAGIC: &[u8] = b"CH8S";
+static VERSION: u8 = 2; // 2: the display is sized for hi-res
+
+static TAG_REGISTERS: u16 = 1;
+static TAG_MEMORY: u16 = 2;
+static TAG_STACK: u16 = 3;
+static TAG_DISPLAY: u16 = 4;
+static TAG_KP_INPUT: u16 = 5;
+static TAG_INDEX: u16 = 6;
+static TAG_PC: u16 = 7;
+static TAG_SP: u16 = 8;
+static TAG_DELAY_TIMER: u16 = 9;
+static TAG_SOUND_TIMER: u16 = 10;
+static TAG_HIRES: u16 = 11;
+static TAG_PLANES: u16 = 12;
+
+fn write_field(bytes: &mut Vec<u8>, tag: u16, value: &[u8]) {
+    bytes.extend_from_slice(&tag.to_be_bytes());
+    bytes.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(value);
+}
+
+pub fn encode(state: &Chip8State) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+
+    write_field(&mut bytes, TAG_REGISTERS, &state.registers);
+    write_field(&mut bytes, TAG_MEMORY, &state.memory);
+    write_field(&mut bytes, TAG_STACK, &state.stack.iter().flat_map(|v| v.to_be_bytes().to_vec()).collect::<Vec<u8>>());
+    write_field(&mut bytes, TAG_DISPLAY, &state.display.iter().map(|v| *v as u8).collect::<Vec<u8>>());
+    write_field(&mut bytes, TAG_KP_INPUT, &state.kp_input.iter().map(|v| *v as u8).collect::<Vec<u8>>());
+    write_field(&mut bytes, TAG_INDEX, &state.index.to_be_bytes());
+    write_field(&mut bytes, TAG_PC, &state.pc.to_be_bytes());
+    write_field(&mut bytes, TAG_SP, &[state.sp]);
+    write_field(&mut bytes, TAG_DELAY_TIMER, &[state.delay_timer]);
+    write_field(&mut bytes, TAG_SOUND_TIMER, &[state.sound_timer]);
+    write_field(&mut bytes, TAG_HIRES, &[state.hires as u8]);
+    write_field(&mut bytes, TAG_PLANES, &[state.planes]);
+
+    bytes
+}
+
+// copies value into target, which has to keep its size so the state still fits the machine it is restored into
+fn read_into<T>(target: &mut [T], value: &[u8], width: usize, convert: fn(&[u8]) -> T, name: &str) -> Result<()> {
+    if value.len() != target.len() * width {
+        bail!("save state {} has {} bytes, expected {}", name, value.len(), target.len() * width);
+    }
+
+    for (ii, chunk) in value.chunks(width).enumerate() {
+        target[ii] = convert(chunk);
+    }
+
+    Ok(())
+}
+
+fn read_u8(value: &[u8], name: &str) -> Result<u8> {
+    match value {
+        [v] => Ok(*v),
+        _ => bail!("save state {} has {} bytes, expected 1", name, value.len())
+    }
+}
+
+fn read_u16(value: &[u8], name: &str) -> Result<u16> {
+    Ok(u16::from_be_bytes(value.try_into().with_context(|| std::format!("save state {} has {} bytes, expected 2", name, value.len()))?))
+}
+
+// applies the fields of a save on top of state
+pub fn decode(bytes: &[u8], state: &mut Chip8State) -> Result<()> {
+    if !bytes.starts_with(MAGIC) {
+        bail!("not a ch8-rs save state");
+    }
+
+    match bytes.get(MAGIC.len()) {
+        Some(version) if *version == VERSION => { }
+        Some(version) => bail!("save state version {} is not supported, expected version {}", version, VERSION),
+        None => bail!("save state is truncated")
+    }
+
+    let mut rest = &bytes[MAGIC.len() + 1..];
+    while !rest.is_empty() {
+        if rest.len() < 6 {
+            bail!("save state is truncated");
+        }
+
+        let tag = u16::from_be_bytes([rest[0], rest[1]]);
+        let len = u32::from_be_bytes([rest[2], rest[3], rest[4], rest[5]]) as usize;
+        if rest.len() < 6 + len {
+            bail!("save state is truncated");
+        }
+
+        let value = &rest[6..6 + len];
+        rest = &rest[6 + len..];
+
+        match tag {
+            t if t == TAG_REGISTERS => read_into(&mut state.registers, value, 1, |b| b[0], "registers")?,
+            t if t == TAG_MEMORY => read_into(&mut state.memory, value, 1, |b| b[0], "memory")?,
+            t if t == TAG_STACK => read_into(&mut state.stack, value, 2, |b| u16::from_be_bytes([b[0], b[1]]), "stack")?,
+            t if t == TAG_DISPLAY => read_into(&mut state.display, value, 1, |b| b[0] as u32, "display")?,
+            t if t == TAG_KP_INPUT => read_into(&mut state.kp_input, value, 1, |b| b[0] as u32, "keypad")?,
+            t if t == TAG_INDEX => state.index = read_u16(value, "index")?,
+            t if t == TAG_PC => state.pc = read_u16(value, "pc")?,
+            t if t == TAG_SP => state.sp = read_u8(value, "sp")?,
+            t if t == TAG_DELAY_TIMER => state.delay_timer = read_u8(value, "delay timer")?,
+            t if t == TAG_SOUND_TIMER => state.sound_timer = read_u8(value, "sound timer")?,
+            t if t == TAG_HIRES => state.hires = read_u8(value, "hires")? != 0,
+            t if t == TAG_PLANES => state.planes = read_u8(value, "planes")? & 0x3,
+            _ => { } // written by a newer version
+        }
+    }
+
+    // sp counts the used stack entries, anything past a full stack would index out of it on the next call or return
+    if state.sp as usize > state.stack.len() {
+        bail!("save state sp {} is past the end of the {} entry stack", state.sp, state.stack.len());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ch8::Chip8;
+
+    // a save with a tag this version doesn't know still loads, the unknown field is skipped
+    #[test]
+    fn skips_unknown_tags() {
+        let mut state = Chip8::default().snapshot();
+        state.pc = 0x234;
+        state.registers[3] = 0x42;
+
+        let mut bytes = encode(&state);
+        write_field(&mut bytes, 0xBEEF, &[1, 2, 3]);
+
+        let mut decoded = Chip8::default().snapshot();
+        decode(&bytes, &mut decoded).unwrap();
+        assert!(decoded == state);
+    }
+
+    #[test]
+    fn rejects_sp_past_stack() {
+        let mut state = Chip8::default().snapshot();
+        state.sp = 16;
+        decode(&encode(&state), &mut Chip8::default().snapshot()).unwrap();
+
+        state.sp = 17;
+        assert!(decode(&encode(&state), &mut Chip8::default().snapshot()).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use image::ColorType;
+
+use ch8_rs::ch8::Chip8;
+
+static MEMORY_IMAGE_WIDTH: usize = 64;
+
+// memory as grayscale pixels, one per byte and 64 bytes per row, and the amount of rows
+// a memory size that isn't a multiple of 64 gets a last row padded with black
+fn memory_image(ch8: &Chip8) -> (Vec<u8>, usize) {
+    let height = ch8.memory_size().div_ceil(MEMORY_IMAGE_WIDTH);
+    let mut pixels = ch8.memory().to_vec();
+    pixels.resize(MEMORY_IMAGE_WIDTH * height, 0);
+
+    (pixels, height)
+}
+
+// writes memory as a grayscale png with one pixel per byte, 64 bytes per row so the 4k of memory is 64x64
+// the font, the loaded rom and empty memory show up as visibly different regions
+pub fn save_memory_png(ch8: &Chip8, path: &str) -> Result<()> {
+    let (pixels, height) = memory_image(ch8);
+
+    image::save_buffer(path, &pixels, MEMORY_IMAGE_WIDTH as u32, height as u32, ColorType::L8)
+        .context("failed to write memory png")
+}
+
+// writes the current display as a png, scaled up like the window
+pub fn save_display_png(ch8: &Chip8, path: &str, scale: usize) -> Result<()> {
+    let (width, height) = ch8.display_dimensions();
+
+    image::save_buffer(path, &ch8.display_rgba(scale), (width * scale) as u32, (height * scale) as u32, ColorType::Rgba8)
+        .with_context(|| std::format!("failed to write screenshot {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ch8_rs::{FONT_START, ROM_START};
+
+    fn brightness(pixels: &[u8]) -> usize {
+        pixels.iter().map(|pixel| *pixel as usize).sum::<usize>() / pixels.len()
+    }
+
+    // the font, the rom and the empty memory after it each have their own brightness
+    #[test]
+    fn shows_font_and_rom_regions() {
+        let rom = include_bytes!("../testroms/test_opcode.ch8");
+        let mut ch8 = Chip8::default();
+        ch8.load_rom_bytes(rom).unwrap();
+
+        let (pixels, height) = memory_image(&ch8);
+        assert_eq!(height, 64);
+
+        let font = brightness(&pixels[FONT_START..FONT_START + 80]);
+        let code = brightness(&pixels[ROM_START..ROM_START + rom.len()]);
+        let empty = brightness(&pixels[ROM_START + rom.len()..]);
+
+        assert!(font > code + 16 && code > empty + 16, "font {} rom {} empty {}", font, code, empty);
+        assert_eq!(empty, 0);
+    }
+
+    // 5000 bytes is 78 full rows and a padded one, the png encoder needs the buffer to fill the image exactly
+    #[test]
+    fn pads_partial_last_row() {
+        let mut ch8 = Chip8::default();
+        ch8.set_memory_size(5000).unwrap();
+
+        let (pixels, height) = memory_image(&ch8);
+        assert_eq!(height, 79);
+        assert_eq!(pixels.len(), 64 * 79);
+
+        let path = std::env::temp_dir().join(std::format!("ch8-rs-memory-{}.png", std::process::id()));
+        save_memory_png(&ch8, path.to_str().unwrap()).unwrap();
+        let image = image::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((image.width(), image.height()), (64, 79));
+    }
+}
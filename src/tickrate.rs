@@ -1,4 +1,5 @@
-use std::{collections::VecDeque, time::{Duration, Instant}};
+use anyhow::{Context, Result};
+use std::{collections::VecDeque, fs::File, io::{BufWriter, Write}, time::{Duration, Instant}};
 
 pub struct Tickrate {
     frame_times: VecDeque<Instant>
@@ -12,14 +13,70 @@ impl Tickrate {
     }
 
     pub fn tick(&mut self) -> usize {
-        let now = Instant::now();
+        self.tick_at(Instant::now())
+    }
+
+    // same as tick but with the current time supplied by the caller, so the window can be driven with synthetic times
+    pub fn tick_at(&mut self, now: Instant) -> usize {
         let last = now - Duration::from_secs(1);
 
-        while self.frame_times.front().map_or(false, |t| *t < last) {
+        while self.frame_times.front().is_some_and(|t| *t < last) {
             self.frame_times.pop_front();
         }
 
         self.frame_times.push_back(now);
         self.frame_times.len()
     }
-}
\ No newline at end of file
+}
+
+// writes one csv row per elapsed second with the fps measured by Tickrate at that moment
+pub struct FpsLog {
+    writer: BufWriter<File>,
+    start: Instant,
+    seconds_logged: u64
+}
+
+impl FpsLog {
+    pub fn create(path: &str) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(path).context("failed to create fps log")?);
+        writeln!(writer, "second,fps")?;
+
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+            seconds_logged: 0
+        })
+    }
+
+    pub fn record(&mut self, fps: usize) -> Result<()> {
+        let second = self.start.elapsed().as_secs();
+        if second <= self.seconds_logged {
+            return Ok(());
+        }
+
+        self.seconds_logged = second;
+        writeln!(self.writer, "{},{}", second, fps)?;
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 60 ticks 16ms apart all count, half a second past them the 32 before 500ms have dropped out, two seconds on all of them
+    #[test]
+    fn counts_ticks_in_the_last_second() {
+        let start = Instant::now();
+        let mut tickrate = Tickrate::new();
+
+        for frame in 0..60 {
+            assert_eq!(tickrate.tick_at(start + Duration::from_millis(frame * 16)), frame as usize + 1);
+        }
+
+        assert_eq!(tickrate.tick_at(start + Duration::from_millis(1500)), 29);
+        assert_eq!(tickrate.tick_at(start + Duration::from_secs(3)), 1);
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::{Error, bail};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::disasm;
+
+// output format of the per instruction trace printed in debug mode
+#[derive(Clone, Copy, PartialEq)]
+pub enum TraceFormat {
+    Text,
+    Json
+}
+
+impl FromStr for TraceFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(TraceFormat::Text),
+            "json" => Ok(TraceFormat::Json),
+            _ => bail!("unknown trace format '{}', expected text or json", s)
+        }
+    }
+}
+
+// instruction families by their disassembler mnemonic
+static FAMILIES: &[&str] = &[
+    "CLS", "RET", "SCD", "SCR", "SCL", "LOW", "HIGH", "PLANE", "SYS", "JP", "CALL", "SE", "SNE", "LD", "ADD", "OR", "AND", "XOR",
+    "SUB", "SHR", "SUBN", "SHL", "RND", "DRW", "SKP", "SKNP", "DW"
+];
+
+// limits the trace to a comma separated list of instruction families, e.g. DRW,CALL,RET
+#[derive(Clone, PartialEq)]
+pub struct TraceFilter {
+    families: Vec<String>
+}
+
+impl TraceFilter {
+    pub fn matches(&self, opcode: u16) -> bool {
+        let mnemonic = disasm::mnemonic(opcode);
+        let family = mnemonic.split_whitespace().next().unwrap_or("");
+
+        self.families.iter().any(|f| f == family)
+    }
+}
+
+impl FromStr for TraceFilter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut families = Vec::new();
+
+        for family in s.split(',').map(|f| f.trim().to_uppercase()) {
+            if !FAMILIES.contains(&family.as_str()) {
+                bail!("unknown instruction family '{}' in trace filter, expected any of {}", family, FAMILIES.join(","));
+            }
+
+            families.push(family);
+        }
+
+        Ok(TraceFilter { families })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RegisterDelta {
+    pub register: u8,
+    pub old: u8,
+    pub new: u8
+}
+
+// a single executed instruction, serialized as one json object per line
+#[derive(Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub pc: usize,
+    pub opcode: u16,
+    pub mnemonic: String,
+    pub registers: Vec<RegisterDelta> // registers changed by the instruction
+}
+
+impl TraceEntry {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
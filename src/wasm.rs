@@ -0,0 +1,90 @@
+use wasm_bindgen::prelude::*;
+
+use crate::ch8::Chip8;
+
+// the emulator as seen from javascript, see web/ for a page that drives it
+#[wasm_bindgen(js_name = Chip8)]
+pub struct WasmChip8 {
+    ch8: Chip8
+}
+
+#[wasm_bindgen(js_class = Chip8)]
+impl WasmChip8 {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self { ch8: Chip8::new() }
+    }
+
+    pub fn load_rom_bytes(&mut self, bytes: &[u8]) -> Result<(), JsValue> {
+        self.ch8.load_rom_bytes(bytes).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    // runs a single instruction, timers included
+    pub fn cycle(&mut self) -> Result<(), JsValue> {
+        self.ch8.cycle().map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    // runs one 60hz frame worth of instructions, meant to be called from requestAnimationFrame
+    pub fn run_frame(&mut self) -> Result<(), JsValue> {
+        self.ch8.run_frame().map(|_| ()).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    // address of the display in wasm memory, display_width * display_height u32 palette indices
+    pub fn display_ptr(&self) -> *const u32 {
+        self.ch8.display().as_ptr()
+    }
+
+    pub fn display_width(&self) -> usize {
+        self.ch8.display_dimensions().0
+    }
+
+    pub fn display_height(&self) -> usize {
+        self.ch8.display_dimensions().1
+    }
+
+    pub fn set_input(&mut self, key: u8, pressed: bool) {
+        self.ch8.set_input(key, pressed);
+    }
+
+    pub fn is_beeping(&self) -> bool {
+        self.ch8.is_beeping()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.ch8.is_halted()
+    }
+}
+
+impl Default for WasmChip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the calls web/index.js makes, run natively, errors need a javascript host for their JsValue so only the ok paths are covered
+    #[test]
+    fn drives_like_the_page() {
+        let mut ch8 = WasmChip8::new();
+
+        // V0 = key, I = font digit V0, draw it at 0,0, then spin
+        assert!(ch8.load_rom_bytes(&[0xF0, 0x0A, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x06]).is_ok());
+
+        ch8.set_input(5, true);
+        assert!(ch8.run_frame().is_ok());
+        ch8.set_input(5, false);
+        assert!(ch8.run_frame().is_ok());
+        assert!(ch8.cycle().is_ok());
+
+        let (width, height) = (ch8.display_width(), ch8.display_height());
+        let display = unsafe { std::slice::from_raw_parts(ch8.display_ptr(), width * height) };
+
+        assert_eq!((width, height), (64, 32));
+        assert_eq!(display[..4], [1, 1, 1, 1]); // top row of the 5 glyph
+        assert!(!ch8.is_halted());
+        assert!(!ch8.is_beeping());
+    }
+}
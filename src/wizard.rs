@@ -0,0 +1,139 @@
+use anyhow::Result;
+use std::io::{self, Write};
+
+use ch8_rs::ch8::{Chip8, QUIRK_FLAGS, Quirks};
+use ch8_rs::error::Ch8Error;
+
+static WIZARD_CYCLES: usize = 200_000; // cycles each configuration is run for
+
+// what a headless run of the rom looked like
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Halted(u16, Ch8Error), // pc and the fault that halted the machine
+    Blank,
+    Drawing(usize) // amount of lit pixels
+}
+
+// the rom run with the current quirks and with every quirk flipped on its own, in QUIRK_FLAGS order
+struct Report {
+    current: Outcome,
+    toggled: Vec<Outcome>
+}
+
+impl Report {
+    // first quirk whose toggle gets a broken rom drawing, None when the rom already draws or no single toggle helps
+    fn suggestion(&self) -> Option<usize> {
+        if let Outcome::Drawing(_) = self.current {
+            return None;
+        }
+
+        self.toggled.iter().position(|outcome| matches!(outcome, Outcome::Drawing(_)))
+    }
+}
+
+fn run(rom: &[u8], quirks: Quirks) -> Result<Outcome, Ch8Error> {
+    let mut ch8 = Chip8::default();
+    ch8.set_strict(true);
+    ch8.set_quirks(quirks);
+    ch8.set_seed(0);
+    ch8.load_rom_bytes(rom)?;
+
+    if let Err(e) = ch8.run_cycles(WIZARD_CYCLES) {
+        return Ok(Outcome::Halted(ch8.get_pc(), e));
+    }
+
+    let (width, height) = ch8.display_dimensions();
+    let lit = (0..width * height).filter(|ii| ch8.get_display(*ii) != 0).count();
+
+    Ok(if lit == 0 { Outcome::Blank } else { Outcome::Drawing(lit) })
+}
+
+fn toggle(mut quirks: Quirks, index: usize) -> Quirks {
+    let flag = QUIRK_FLAGS[index].1;
+    *flag(&mut quirks) = !*flag(&mut quirks);
+    quirks
+}
+
+fn report(rom: &[u8], quirks: Quirks) -> Result<Report, Ch8Error> {
+    Ok(Report {
+        current: run(rom, quirks)?,
+        toggled: (0..QUIRK_FLAGS.len()).map(|ii| run(rom, toggle(quirks, ii))).collect::<Result<_, _>>()?
+    })
+}
+
+fn describe(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Halted(pc, e) => std::format!("halted at {:#05X}: {}", pc, e),
+        Outcome::Blank => std::format!("screen still blank after {} cycles", WIZARD_CYCLES),
+        Outcome::Drawing(lit) => std::format!("running, {} pixels lit", lit)
+    }
+}
+
+// runs the rom headless and lets the user toggle quirks until it behaves, returns the chosen quirks
+pub fn interactive_quirks(rom: &str, mut quirks: Quirks) -> Result<Quirks> {
+    let bytes = std::fs::read(rom).map_err(|_| Ch8Error::RomNotFound(String::from(rom)))?;
+
+    loop {
+        let report = report(&bytes, quirks)?;
+        println!("{}", describe(&report.current));
+
+        if let Outcome::Drawing(_) = report.current {
+            println!("this configuration looks fine, other quirks may still change how the rom plays");
+        } else {
+            println!("this looks broken, toggling a quirk might help");
+        }
+
+        for (ii, ((name, flag), toggled)) in QUIRK_FLAGS.iter().zip(&report.toggled).enumerate() {
+            println!("  {}) {:<24} {:<5} -> toggled: {}", ii + 1, name, *flag(&mut quirks), describe(toggled));
+        }
+
+        if let Some(ii) = report.suggestion() {
+            println!("toggling {} ({}) gets the rom drawing", QUIRK_FLAGS[ii].0, ii + 1);
+        }
+
+        print!("toggle quirk (number), or press enter to start with this configuration: ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+
+        match line.trim().parse::<usize>() {
+            Ok(choice) if choice >= 1 && choice <= QUIRK_FLAGS.len() => quirks = toggle(quirks, choice - 1),
+            _ if line.trim().is_empty() => return Ok(quirks),
+            _ => println!("invalid choice")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // shifts 0x10 in V0 with 0x02 in V1, returns without a call unless the shift came out of V1 (cosmac vip), else draws
+    static SHIFT_ROM: &[u8] = &[0x60, 0x10, 0x61, 0x02, 0x80, 0x16, 0x30, 0x01, 0x00, 0xEE, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x0E];
+
+    fn shift_uses_vy() -> usize {
+        QUIRK_FLAGS.iter().position(|(name, _)| *name == "shift_uses_vy").unwrap()
+    }
+
+    #[test]
+    fn guides_shift_rom_to_working_quirks() {
+        let broken = report(SHIFT_ROM, Quirks::default()).unwrap();
+        assert_eq!(broken.current, Outcome::Halted(0x208, Ch8Error::StackUnderflow));
+        assert_eq!(broken.suggestion(), Some(shift_uses_vy()));
+
+        let quirks = toggle(Quirks::default(), shift_uses_vy());
+        assert!(quirks.shift_uses_vy);
+
+        let fixed = report(SHIFT_ROM, quirks).unwrap();
+        assert!(matches!(fixed.current, Outcome::Drawing(_)));
+        assert_eq!(fixed.suggestion(), None);
+    }
+
+    // the actual fault is shown, not a guess at what went wrong
+    #[test]
+    fn describes_the_fault() {
+        let outcome = Outcome::Halted(0x208, Ch8Error::StackUnderflow);
+        assert_eq!(describe(&outcome), "halted at 0x208: stack underflow, return without a call");
+    }
+}
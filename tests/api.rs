@@ -0,0 +1,57 @@
+// the library as another crate sees it, only the public api re-exported from lib.rs is used here
+use ch8_rs::{Chip8, Chip8Builder, NullRenderer, Quirks, ROM_START, VIDEO_WIDTH};
+
+// V0 = 0x12, then count V0 up forever
+static COUNT_PROGRAM: &[u8] = &[0x60, 0x12, 0x70, 0x01, 0x12, 0x02];
+
+#[test]
+fn runs_a_loaded_rom() {
+    let mut ch8 = Chip8::new();
+    ch8.load_rom_bytes(&[0x60, 0x12, 0x61, 0x34, 0x80, 0x14, 0x12, 0x06]).unwrap();
+
+    assert_eq!(ch8.run_cycles(3).unwrap(), 3);
+    assert_eq!(ch8.get_register(0), 0x46);
+    assert_eq!(ch8.get_register(1), 0x34);
+    assert_eq!(ch8.get_pc(), ROM_START as u16 + 6);
+}
+
+#[test]
+fn draws_and_presents_a_frame() {
+    let mut ch8 = Chip8::new();
+
+    // I = font digit 0, draw it at 0,0
+    ch8.load_rom_bytes(&[0xA0, 0x50, 0xD0, 0x05, 0x12, 0x04]).unwrap();
+    ch8.run_until_draw().unwrap();
+
+    assert_eq!(ch8.display()[..4], [1, 1, 1, 1]);
+    assert_eq!(ch8.display()[VIDEO_WIDTH..VIDEO_WIDTH + 4], [1, 0, 0, 1]);
+    assert!(ch8.present(&mut NullRenderer));
+    assert!(!ch8.present(&mut NullRenderer));
+}
+
+#[test]
+fn restores_a_snapshot() {
+    let mut ch8 = Chip8Builder::new().seed(1).build();
+    ch8.load_rom_bytes(COUNT_PROGRAM).unwrap();
+    ch8.run_cycles(1).unwrap();
+
+    let state = ch8.snapshot();
+    ch8.run_cycles(3).unwrap();
+    assert!(ch8.snapshot() != state);
+
+    ch8.restore(&state);
+    assert!(ch8.snapshot() == state);
+    assert_eq!(ch8.get_register(0), 0x12);
+}
+
+#[test]
+fn applies_quirks() {
+    let mut ch8 = Chip8::new();
+    ch8.set_quirks(Quirks { jump_uses_vx: true, ..Quirks::default() });
+
+    // V2 = 4, B208 jumps to 0x208 + V2 with the quirk instead of 0x208 + V0
+    ch8.load_rom_bytes(&[0x62, 0x04, 0xB2, 0x08]).unwrap();
+    ch8.run_cycles(2).unwrap();
+
+    assert_eq!(ch8.get_pc(), 0x20C);
+}
@@ -0,0 +1,39 @@
+// inputs the cycle fuzz target (fuzz/fuzz_targets/cycle.rs) found panics with, run the same way so they stay fixed
+use ch8_rs::{Chip8, MEMORY_SIZE, ROM_START};
+
+static MAX_CYCLES: usize = 10_000;
+
+fn run(data: &[u8]) -> Chip8 {
+    let mut ch8 = Chip8::new();
+    ch8.load_rom_bytes(&data[..data.len().min(MEMORY_SIZE - ROM_START)]).unwrap();
+
+    for _ in 0..MAX_CYCLES {
+        if ch8.is_halted() {
+            break;
+        }
+
+        let _ = ch8.cycle();
+    }
+
+    ch8
+}
+
+// Ex9E/ExA1 indexed the keypad with the whole register instead of a key
+#[test]
+fn key_skip_with_register_above_0xf() {
+    run(&[0xEF, 0x74, 0x4D, 0x33, 0x86, 0x9F, 0xF4, 0xCB, 0x6F, 0x65, 0xEF, 0xA1, 0x02, 0x5F, 0x13, 0x14]);
+    run(&[0xA2, 0x80, 0x60, 0xF8, 0xE0, 0x9E, 0x3C, 0x46, 0x9A, 0x81, 0x84, 0x45, 0x67, 0x0F, 0x74, 0x26]);
+    run(&[0xD1, 0xF2, 0xEA, 0x21, 0xCA, 0xA1, 0xDA, 0xCA, 0xC1, 0x39, 0xEA, 0xA1, 0x30, 0xB8, 0xDE, 0xE4]);
+}
+
+// only the low nibble selects the key, V0 = 0x15 reads key 5
+#[test]
+fn key_skip_uses_low_nibble() {
+    let mut ch8 = Chip8::new();
+    ch8.load_rom_bytes(&[0x60, 0x15, 0xE0, 0x9E, 0x6A, 0x01, 0x6B, 0x01]).unwrap();
+    ch8.set_input(5, true);
+    ch8.run_cycles(3).unwrap();
+
+    assert_eq!(ch8.get_register(0xA), 0);
+    assert_eq!(ch8.get_register(0xB), 1);
+}
@@ -0,0 +1,56 @@
+// runs every rom listed in tests/roms/hashes.txt headless and compares the final display against its reference hash
+use std::fs;
+use std::path::Path;
+
+use ch8_rs::Chip8;
+
+static ROMS: &str = "tests/roms";
+
+// (rom, cycles, hash) for every line that isn't blank or a comment
+fn references(listing: &str) -> Vec<(String, usize, u64)> {
+    listing.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(fields.len(), 3, "expected `<rom> <cycles> <hash>`, got `{}`", line);
+
+            let cycles = fields[1].parse().unwrap_or_else(|_| panic!("invalid cycle count in `{}`", line));
+            let hash = u64::from_str_radix(fields[2], 16).unwrap_or_else(|_| panic!("invalid hash in `{}`", line));
+            (fields[0].to_string(), cycles, hash)
+        })
+        .collect()
+}
+
+fn run(rom: &str, cycles: usize) -> Chip8 {
+    let mut ch8 = Chip8::new();
+    ch8.read_rom(&Path::new(ROMS).join(rom).to_string_lossy()).unwrap_or_else(|e| panic!("{}: {}", rom, e));
+    ch8.run_cycles(cycles).unwrap_or_else(|e| panic!("{}: {}", rom, e));
+    ch8
+}
+
+#[test]
+fn roms_match_reference_hashes() {
+    let path = Path::new(ROMS).join("hashes.txt");
+    let listing = fs::read_to_string(&path).unwrap();
+
+    // CH8_BLESS=1 rewrites the hashes from the current output instead of comparing against them
+    if std::env::var_os("CH8_BLESS").is_some() {
+        let blessed: Vec<String> = listing.lines().map(|line| match references(line).first() {
+            Some((rom, cycles, _)) => std::format!("{} {} {:016x}", rom, cycles, run(rom, *cycles).display_hash()),
+            None => line.to_string()
+        }).collect();
+
+        fs::write(&path, blessed.join("\n") + "\n").unwrap();
+        return;
+    }
+
+    let failures: Vec<String> = references(&listing).iter()
+        .filter_map(|(rom, cycles, hash)| {
+            let actual = run(rom, *cycles).display_hash();
+            if actual != *hash { Some(std::format!("{}: expected {:016x}, got {:016x}", rom, hash, actual)) } else { None }
+        })
+        .collect();
+
+    assert!(failures.is_empty(), "display hashes differ:\n{}", failures.join("\n"));
+}
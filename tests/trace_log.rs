@@ -0,0 +1,87 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::sync::{Mutex, MutexGuard, Once};
+
+use ch8_rs::Chip8;
+use ch8_rs::trace::TraceFormat;
+
+// keeps every log line so the instruction trace can be checked, the logger is global so this lives in its own test binary
+struct CaptureLogger {
+    lines: Mutex<Vec<String>>
+}
+
+impl Log for CaptureLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.lines.lock().unwrap().push(format!("{} {}", record.level(), record.args()));
+    }
+
+    fn flush(&self) { }
+}
+
+static CAPTURE_LOGGER: CaptureLogger = CaptureLogger { lines: Mutex::new(Vec::new()) };
+static INSTALL: Once = Once::new();
+static SERIAL: Mutex<()> = Mutex::new(());
+
+// installs the logger once and keeps the tests from logging over each other, the lines start out empty
+fn capture() -> MutexGuard<'static, ()> {
+    INSTALL.call_once(|| log::set_logger(&CAPTURE_LOGGER).unwrap());
+
+    let guard = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    CAPTURE_LOGGER.lines.lock().unwrap().clear();
+
+    guard
+}
+
+fn captured() -> Vec<String> {
+    CAPTURE_LOGGER.lines.lock().unwrap().clone()
+}
+
+// the instruction trace goes through the log crate at the trace level and is silent below it
+#[test]
+fn logs_instruction_trace() {
+    let _guard = capture();
+
+    let mut ch8 = Chip8::default();
+    ch8.load_rom_bytes(&[0x60, 0x05, 0x70, 0x01, 0x60, 0x07]).unwrap();
+
+    log::set_max_level(LevelFilter::Trace);
+    ch8.run_cycles(2).unwrap();
+    log::set_max_level(LevelFilter::Debug);
+    ch8.run_cycles(1).unwrap();
+    log::set_max_level(LevelFilter::Off);
+
+    assert_eq!(captured(), ["TRACE LD V0, 0x05", "TRACE ADD V0, 0x01"]);
+}
+
+// every json trace line is one object with the pc, opcode, mnemonic and the registers the instruction changed
+#[test]
+fn logs_json_trace() {
+    let _guard = capture();
+
+    let mut ch8 = Chip8::default();
+    ch8.set_trace_format(TraceFormat::Json);
+    ch8.load_rom_bytes(&[0x60, 0x05, 0x70, 0x01, 0xA3, 0x00]).unwrap();
+
+    log::set_max_level(LevelFilter::Trace);
+    ch8.run_cycles(3).unwrap();
+    log::set_max_level(LevelFilter::Off);
+
+    let entries: Vec<serde_json::Value> = captured().iter()
+        .map(|line| serde_json::from_str(line.trim_start_matches("TRACE ")).unwrap())
+        .collect();
+
+    assert_eq!(entries.len(), 3);
+    for (entry, pc) in entries.iter().zip(&[0x200, 0x202, 0x204]) {
+        assert_eq!(entry["pc"], *pc);
+        assert!(entry["opcode"].is_u64());
+        assert!(entry["registers"].is_array());
+    }
+
+    assert_eq!(entries[1]["opcode"], 0x7001);
+    assert_eq!(entries[1]["mnemonic"], "ADD V0, 0x01");
+    assert_eq!(entries[1]["registers"], serde_json::json!([{ "register": 0, "old": 5, "new": 6 }]));
+    assert_eq!(entries[2]["registers"], serde_json::json!([]));
+}
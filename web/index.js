@@ -0,0 +1,72 @@
+// minimal page driving the wasm build, see the README for building pkg/ with wasm-bindgen
+import init, { Chip8 } from "./pkg/ch8_rs.js";
+
+// same layout as the desktop default keymap
+const KEYS = {
+    "1": 0x1, "2": 0x2, "3": 0x3, "4": 0xC,
+    "q": 0x4, "w": 0x5, "e": 0x6, "r": 0xD,
+    "a": 0x7, "s": 0x8, "d": 0x9, "f": 0xE,
+    "z": 0xA, "x": 0x0, "c": 0xB, "v": 0xF
+};
+
+// argb palette by plane bits, background, plane 1, plane 2 and both planes
+const PALETTE = [0xFF000000, 0xFFFFFFFF, 0xFF808080, 0xFFC0C0C0];
+
+const wasm = await init();
+const canvas = document.getElementById("display");
+const context = canvas.getContext("2d");
+const error = document.getElementById("error");
+let ch8 = null;
+
+// a rom that doesn't fit or faults while running is reported under the canvas, the machine stays halted
+document.getElementById("rom").addEventListener("change", async (event) => {
+    const file = event.target.files[0];
+    const bytes = new Uint8Array(await file.arrayBuffer());
+    const next = new Chip8();
+    error.textContent = "";
+
+    try {
+        next.load_rom_bytes(bytes);
+        ch8 = next;
+    } catch (e) {
+        error.textContent = `${file.name}: ${e}`;
+        ch8 = null;
+    }
+});
+
+for (const [type, pressed] of [["keydown", true], ["keyup", false]]) {
+    document.addEventListener(type, (event) => {
+        const key = KEYS[event.key.toLowerCase()];
+        if (ch8 && key !== undefined) {
+            ch8.set_input(key, pressed);
+        }
+    });
+}
+
+function frame() {
+    if (ch8 && !ch8.is_halted()) {
+        try {
+            ch8.run_frame();
+        } catch (e) {
+            error.textContent = `halted: ${e}`;
+        }
+
+        const width = ch8.display_width();
+        const height = ch8.display_height();
+        const display = new Uint32Array(wasm.memory.buffer, ch8.display_ptr(), width * height);
+        const image = context.createImageData(width, height);
+
+        display.forEach((planes, i) => {
+            const argb = PALETTE[planes & 0x3];
+            image.data.set([(argb >> 16) & 0xFF, (argb >> 8) & 0xFF, argb & 0xFF, 0xFF], i * 4);
+        });
+
+        canvas.width = width;
+        canvas.height = height;
+        context.putImageData(image, 0, 0);
+    }
+
+    requestAnimationFrame(frame);
+}
+
+requestAnimationFrame(frame);